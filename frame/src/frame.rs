@@ -25,6 +25,26 @@ impl Frame {
     }
 }
 
+/// Index of the frame in flight.
+/// Selects per-frame resources cycling through `0 .. frames_in_flight`.
+///
+/// Distinct from index of the swapchain image (see `rendy_wsi::ImageIndex`)
+/// which is picked by presentation engine.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FrameIndex(pub u32);
+
+impl From<u32> for FrameIndex {
+    fn from(index: u32) -> Self {
+        FrameIndex(index)
+    }
+}
+
+impl From<FrameIndex> for u32 {
+    fn from(index: FrameIndex) -> Self {
+        index.0
+    }
+}
+
 /// Proof that frame is complete.
 #[derive(Debug)]
 #[allow(missing_copy_implementations)]
@@ -91,6 +111,16 @@ where
         Frame { index: self.next }
     }
 
+    /// Get index in flight of the next frame.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `frames_in_flight` is zero.
+    pub fn current(&self, frames_in_flight: u32) -> FrameIndex {
+        assert_ne!(frames_in_flight, 0, "At least one frame must be in flight");
+        FrameIndex((self.next % u64::from(frames_in_flight)) as u32)
+    }
+
     /// Advance to the next frame.
     /// All fences of the next frame must be queued.
     pub fn advance(&mut self, fences: Fences<B>) {
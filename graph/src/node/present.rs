@@ -12,7 +12,7 @@ use crate::{
         gfx_acquire_barriers, gfx_release_barriers, BufferAccess, DynNode, ImageAccess, NodeBuffer,
         NodeBuilder, NodeImage,
     },
-    wsi::{Surface, Target},
+    wsi::{Surface, Target, Vsync},
     BufferId, ImageId, NodeId,
 };
 
@@ -46,6 +46,7 @@ pub struct PresentNode<B: gfx_hal::Backend> {
     pool: CommandPool<B, gfx_hal::QueueType>,
     input_image: NodeImage,
     blit_filter: gfx_hal::image::Filter,
+    recreate: bool,
 }
 
 // Raw pointer destroys Send/Sync autoimpl, but it's always from the same graph.
@@ -59,25 +60,17 @@ where
     /// Node builder.
     /// By default attempts to use 3 images in the swapchain with present mode priority:
     ///
-    /// Mailbox > Fifo > Relaxed > Immediate.
+    /// Fifo > Relaxed > Mailbox > Immediate.
     ///
     /// You can query the real image count and present mode which will be used with
     /// `PresentBuilder::image_count()` and `PresentBuilder::present_mode()`.
     pub fn builder(factory: &Factory<B>, surface: Surface<B>, image: ImageId) -> PresentBuilder<B> {
         let (caps, _f, present_modes_caps) = factory.get_surface_compatibility(&surface);
 
-        let img_count_caps = caps.image_count;
+        let img_count_caps = crate::wsi::image_count_range(&caps);
         let image_count = 3.min(img_count_caps.end).max(img_count_caps.start);
 
-        let present_mode = *present_modes_caps
-            .iter()
-            .max_by_key(|mode| match mode {
-                gfx_hal::PresentMode::Fifo => 3,
-                gfx_hal::PresentMode::Mailbox => 2,
-                gfx_hal::PresentMode::Relaxed => 1,
-                gfx_hal::PresentMode::Immediate => 0,
-            })
-            .unwrap();
+        let present_mode = Vsync::On.pick(&present_modes_caps).unwrap();
 
         PresentBuilder {
             surface,
@@ -424,10 +417,86 @@ where
             per_image,
             input_image,
             blit_filter: self.blit_filter,
+            recreate: false,
         }))
     }
 }
 
+impl<B> PresentNode<B>
+where
+    B: gfx_hal::Backend,
+{
+    /// Recreate swapchain and per-image data.
+    /// On failure error is logged and recreation is retried next frame.
+    unsafe fn recreate_swapchain(
+        &mut self,
+        ctx: &GraphContext<B>,
+        factory: &Factory<B>,
+    ) -> bool {
+        // Retry next frame until recreation succeeds.
+        self.recreate = true;
+
+        // TODO: use retired swapchains once available in hal and remove that wait
+        if let Err(err) = factory.wait_idle() {
+            log::error!("Failed to wait device idle before recreating swapchain: {}", err);
+            return false;
+        }
+
+        let extent = ctx
+            .get_image(self.input_image.id)
+            .expect("Context must contain node's image")
+            .kind()
+            .extent()
+            .into();
+
+        if let Err(err) = self.target.recreate(factory.physical(), factory.device(), extent) {
+            log::error!("Failed recreating swapchain: {}", err);
+            return false;
+        }
+
+        for data in self.per_image.drain(..) {
+            data.dispose(factory, &mut self.pool);
+        }
+
+        match create_per_image_data(
+            ctx,
+            &self.input_image,
+            &mut self.pool,
+            factory,
+            &self.target,
+            self.blit_filter,
+        ) {
+            Ok(per_image) => self.per_image = per_image,
+            Err(err) => {
+                log::error!("Failed recreating swapchain data: {}", err);
+                return false;
+            }
+        }
+
+        self.recreate = false;
+        true
+    }
+
+    /// Skip presentation this frame.
+    /// Semaphores and fence are still waited and signaled
+    /// so that the rest of the graph is not stalled.
+    unsafe fn skip_frame<'a>(
+        queue: &mut Queue<B>,
+        waits: &[(&'a B::Semaphore, gfx_hal::pso::PipelineStage)],
+        signals: &[&'a B::Semaphore],
+        fence: Option<&mut Fence<B>>,
+    ) {
+        queue.submit(
+            Some(
+                Submission::new()
+                    .wait(waits.iter().cloned())
+                    .signal(signals.iter().cloned()),
+            ),
+            fence,
+        );
+    }
+}
+
 impl<B, T> DynNode<B, T> for PresentNode<B>
 where
     B: gfx_hal::Backend,
@@ -444,11 +513,19 @@ where
         signals: &[&'a B::Semaphore],
         mut fence: Option<&mut Fence<B>>,
     ) {
+        if self.recreate {
+            // Previous presentation reported that swapchain doesn't match the surface.
+            if !self.recreate_swapchain(ctx, factory) {
+                Self::skip_frame(queue, waits, signals, fence);
+                return;
+            }
+        }
+
         loop {
             match self.target.next_image(&self.free_acquire) {
                 Ok(next) => {
                     log::trace!("Present: {:#?}", next);
-                    let ref mut for_image = self.per_image[next[0] as usize];
+                    let ref mut for_image = self.per_image[next[0].0 as usize];
                     core::mem::swap(&mut for_image.acquire, &mut self.free_acquire);
 
                     queue.submit(
@@ -465,16 +542,22 @@ where
                     );
 
                     match next.present(queue.raw(), Some(&for_image.release)) {
-                        Ok(_) => break,
+                        Ok(None) => {}
+                        Ok(Some(_)) => {
+                            log::debug!("Swapchain is suboptimal for the surface");
+                            // recreate swapchain on next frame.
+                            self.recreate = true;
+                        }
                         Err(e) => {
                             log::debug!(
                                 "Swapchain present error after next_image is acquired: {}",
                                 e
                             );
                             // recreate swapchain on next frame.
-                            break;
+                            self.recreate = true;
                         }
                     }
+                    break;
                 }
                 Err(gfx_hal::window::AcquireError::OutOfDate) => {
                     // recreate swapchain and try again.
@@ -486,34 +569,10 @@ where
             }
             // Recreate swapchain when OutOfDate
             // The code has to execute after match due to mutable aliasing issues.
-
-            // TODO: use retired swapchains once available in hal and remove that wait
-            factory.wait_idle().unwrap();
-
-            let extent = ctx
-                .get_image(self.input_image.id)
-                .expect("Context must contain node's image")
-                .kind()
-                .extent()
-                .into();
-
-            self.target
-                .recreate(factory.physical(), factory.device(), extent)
-                .expect("Failed recreating swapchain");
-
-            for data in self.per_image.drain(..) {
-                data.dispose(factory, &mut self.pool);
+            if !self.recreate_swapchain(ctx, factory) {
+                Self::skip_frame(queue, waits, signals, fence);
+                return;
             }
-
-            self.per_image = create_per_image_data(
-                ctx,
-                &self.input_image,
-                &mut self.pool,
-                factory,
-                &self.target,
-                self.blit_filter,
-            )
-            .expect("Failed recreating swapchain data");
         }
     }
 
@@ -0,0 +1,172 @@
+//!
+//! Minimal window example.
+//! Opens a window and clears it with solid color every frame.
+//! Swapchain is recreated when window is resized or swapchain becomes out of date.
+//!
+
+#![cfg_attr(
+    not(any(feature = "dx12", feature = "metal", feature = "vulkan")),
+    allow(unused)
+)]
+
+use {
+    gfx_hal::{
+        command::RawCommandBuffer as _, pool::RawCommandPool as _, queue::RawCommandQueue as _,
+        window::Extent2D, Device as _,
+    },
+    rendy_util::Instance,
+    rendy_wsi::{
+        winit::{Event, EventsLoop, Window, WindowBuilder, WindowEvent},
+        AutoTarget, ImageIndex, Target, WindowHandle,
+    },
+};
+
+#[cfg(feature = "dx12")]
+use rendy_util::dx12 as backend;
+
+#[cfg(feature = "metal")]
+use rendy_util::metal as backend;
+
+#[cfg(feature = "vulkan")]
+use rendy_util::vulkan as backend;
+
+const CLEAR_COLOR: [f32; 4] = [0.3, 0.5, 0.8, 1.0];
+
+/// Record command buffer for each swapchain image
+/// that clears the image and transitions it into `Present` layout.
+///
+/// Images of new swapchain are in `Undefined` layout, so recorded transitions
+/// discard image contents. That stays valid when buffers are reused every frame.
+unsafe fn record_clears<B: gfx_hal::Backend>(
+    pool: &mut B::CommandPool,
+    target: &Target<B>,
+) -> Vec<B::CommandBuffer> {
+    (0..target.image_count())
+        .map(|index| {
+            let mut buffer = pool.allocate_one(gfx_hal::command::RawLevel::Primary);
+            buffer.begin(
+                gfx_hal::command::CommandBufferFlags::SIMULTANEOUS_USE,
+                Default::default(),
+            );
+
+            target
+                .clear_image(
+                    &mut buffer,
+                    ImageIndex(index),
+                    gfx_hal::command::ClearColor::Float(CLEAR_COLOR),
+                )
+                .unwrap();
+
+            buffer.finish();
+            buffer
+        })
+        .collect()
+}
+
+fn window_extent(window: &Window) -> Extent2D {
+    WindowHandle::inner_size(window).unwrap()
+}
+
+#[cfg(any(feature = "dx12", feature = "metal", feature = "vulkan"))]
+fn main() {
+    env_logger::Builder::from_default_env()
+        .filter_module("window", log::LevelFilter::Trace)
+        .init();
+
+    let mut event_loop = EventsLoop::new();
+
+    let window = WindowBuilder::new()
+        .with_title("Rendy window example")
+        .build(&event_loop)
+        .unwrap();
+
+    event_loop.poll_events(|_| ());
+
+    let instance: Instance<backend::Backend> = Instance::new(backend::Instance::create("Rendy", 1));
+
+    let AutoTarget {
+        mut target,
+        device,
+        adapter,
+        family,
+        mut queue,
+    } = Target::auto(&instance, &window, 3, gfx_hal::image::Usage::TRANSFER_DST).unwrap();
+
+    unsafe {
+        let mut pool = device
+            .create_command_pool(family, gfx_hal::pool::CommandPoolCreateFlags::empty())
+            .unwrap();
+        let mut buffers = record_clears(&mut pool, &target);
+
+        let acquire = device.create_semaphore().unwrap();
+        let release = device.create_semaphore().unwrap();
+        let fence = device.create_fence(false).unwrap();
+
+        let mut running = true;
+        let mut recreate = false;
+
+        while running {
+            event_loop.poll_events(|event| match event {
+                Event::WindowEvent {
+                    event: WindowEvent::CloseRequested,
+                    ..
+                } => running = false,
+                Event::WindowEvent {
+                    event: WindowEvent::Resized(_),
+                    ..
+                } => recreate = true,
+                _ => {}
+            });
+
+            if recreate {
+                queue.wait_idle().unwrap();
+                target
+                    .recreate(&adapter.physical_device, &device, window_extent(&window))
+                    .unwrap();
+                pool.free(buffers.drain(..));
+                buffers = record_clears(&mut pool, &target);
+                recreate = false;
+            }
+
+            let next = match target.next_image(&acquire) {
+                Ok(next) => next,
+                Err(gfx_hal::AcquireError::OutOfDate) => {
+                    recreate = true;
+                    continue;
+                }
+                Err(err) => panic!("Failed to acquire image: {}", err),
+            };
+
+            queue.submit(
+                gfx_hal::queue::Submission {
+                    command_buffers: Some(&buffers[next[0].0 as usize]),
+                    wait_semaphores: Some((&acquire, gfx_hal::pso::PipelineStage::TRANSFER)),
+                    signal_semaphores: Some(&release),
+                },
+                Some(&fence),
+            );
+
+            if let Err(err) = next.present(&mut queue, Some(&release)) {
+                log::debug!("Failed to present: {}", err);
+                recreate = true;
+            }
+
+            device.wait_for_fence(&fence, !0).unwrap();
+            device.reset_fence(&fence).unwrap();
+        }
+
+        queue.wait_idle().unwrap();
+
+        device.destroy_fence(fence);
+        device.destroy_semaphore(release);
+        device.destroy_semaphore(acquire);
+        pool.free(buffers);
+        device.destroy_command_pool(pool);
+        target.dispose(&device).dispose(&instance);
+    }
+}
+
+#[cfg(not(any(feature = "dx12", feature = "metal", feature = "vulkan")))]
+fn main() {
+    panic!("Specify feature: { dx12, metal, vulkan }");
+}
@@ -0,0 +1,131 @@
+//! Adapter selection for presentation.
+
+use {
+    crate::Surface,
+    gfx_hal::{
+        adapter::DeviceType,
+        queue::{QueueFamily as _, QueueFamilyId},
+        Adapter, Backend,
+    },
+    rendy_util::{identical_cast, rendy_backend_match, Instance},
+};
+
+/// Adapter capable of presenting to particular surface.
+#[derive(derivative::Derivative)]
+#[derivative(Debug)]
+pub struct CompatibleAdapter<B: Backend> {
+    /// Adapter itself.
+    #[derivative(Debug = "ignore")]
+    pub adapter: Adapter<B>,
+
+    /// Queue family that supports graphics and presentation to the surface.
+    pub family: QueueFamilyId,
+}
+
+/// Enumerate all adapters available through the `instance`.
+#[allow(unused)]
+pub fn enumerate_adapters<B: Backend>(instance: &Instance<B>) -> Vec<Adapter<B>> {
+    rendy_backend_match!(B as backend => {
+        let instance = instance.raw_typed::<backend::Instance>().unwrap();
+        identical_cast(gfx_hal::Instance::enumerate_adapters(instance))
+    });
+}
+
+/// Enumerate adapters that can present to the `surface`.
+/// Each one is returned with queue family
+/// that supports both graphics and presentation.
+/// Adapters without such family are skipped.
+pub fn enumerate_surface_compatible_adapters<B: Backend>(
+    instance: &Instance<B>,
+    surface: &Surface<B>,
+) -> Vec<CompatibleAdapter<B>> {
+    surface.assert_instance_owner(instance);
+
+    enumerate_adapters(instance)
+        .into_iter()
+        .filter_map(|adapter| {
+            let family = compatible_family(&adapter, surface)?;
+            Some(CompatibleAdapter { adapter, family })
+        })
+        .collect()
+}
+
+/// Find queue family of the `adapter`
+/// that supports both graphics and presentation to the `surface`.
+pub fn compatible_family<B: Backend>(
+    adapter: &Adapter<B>,
+    surface: &Surface<B>,
+) -> Option<QueueFamilyId> {
+    adapter
+        .queue_families
+        .iter()
+        .find(|family| {
+            family.supports_graphics()
+                && gfx_hal::Surface::supports_queue_family(surface.raw(), family)
+        })
+        .map(|family| family.id())
+}
+
+/// Adapter type preference.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AdapterPreference {
+    /// Prefer discrete GPUs over integrated ones.
+    Discrete,
+
+    /// Prefer integrated GPUs over discrete ones.
+    Integrated,
+
+    /// Prefer adapters that consume less power.
+    ///
+    /// gfx-hal doesn't report power consumption of adapters,
+    /// so this is the same as `Integrated`.
+    LowPower,
+
+    /// Prefer adapters with higher performance.
+    ///
+    /// gfx-hal doesn't report performance of adapters,
+    /// so this is the same as `Discrete`.
+    HighPerformance,
+}
+
+impl Default for AdapterPreference {
+    fn default() -> Self {
+        AdapterPreference::Discrete
+    }
+}
+
+impl AdapterPreference {
+    /// Get priority of the device type for this preference.
+    /// Higher value means higher priority.
+    pub fn priority(&self, device_type: &DeviceType) -> usize {
+        let integrated = match *self {
+            AdapterPreference::Discrete | AdapterPreference::HighPerformance => false,
+            AdapterPreference::Integrated | AdapterPreference::LowPower => true,
+        };
+
+        match *device_type {
+            DeviceType::DiscreteGpu if integrated => 3,
+            DeviceType::DiscreteGpu => 4,
+            DeviceType::IntegratedGpu if integrated => 4,
+            DeviceType::IntegratedGpu => 3,
+            DeviceType::VirtualGpu => 2,
+            DeviceType::Cpu => 1,
+            DeviceType::Other => 0,
+        }
+    }
+}
+
+/// Pick index of the best adapter according to `preference`.
+/// Adapters of other types are picked if no adapter of preferred type is available.
+/// Returns `None` if `adapters` is empty.
+pub fn pick_best<B: Backend>(
+    adapters: &[CompatibleAdapter<B>],
+    preference: AdapterPreference,
+) -> Option<usize> {
+    adapters
+        .iter()
+        .enumerate()
+        .max_by_key(|(_, compatible)| preference.priority(&compatible.adapter.info.device_type))
+        .map(|(index, _)| index)
+}
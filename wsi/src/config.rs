@@ -0,0 +1,550 @@
+//! Swapchain configuration.
+
+use {
+    gfx_hal::{
+        format::{ChannelType, Format, SurfaceType},
+        image::Usage,
+        window::Extent2D,
+        PresentMode,
+    },
+    rendy_memory::MemoryUsageValue,
+};
+
+/// Maximal number of formats in [`TargetConfig::format_priority`].
+///
+/// [`TargetConfig::format_priority`]: struct.TargetConfig.html#structfield.format_priority
+pub const MAX_FORMAT_PRIORITY: usize = 8;
+
+/// Vertical synchronization intent.
+/// Resolves into one of the present modes supported by the surface.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Vsync {
+    /// Synchronize presentation with vertical blank.
+    ///
+    /// Present mode priority: `Fifo` > `Relaxed` > `Mailbox` > `Immediate`.
+    ///
+    /// Note that `Relaxed` waits for vertical blank only if image is ready in time.
+    /// Late images are presented immediately and may cause visible tearing
+    /// when frame rate drops below display refresh rate.
+    On,
+
+    /// Present images as soon as possible.
+    ///
+    /// Present mode priority: `Immediate` > `Mailbox` > `Relaxed` > `Fifo`.
+    Off,
+}
+
+impl Default for Vsync {
+    fn default() -> Self {
+        Vsync::On
+    }
+}
+
+impl Vsync {
+    /// Get priority of the present mode for this intent.
+    /// Higher value means higher priority.
+    pub fn priority(&self, present_mode: PresentMode) -> usize {
+        match (*self, present_mode) {
+            (Vsync::On, PresentMode::Fifo) => 3,
+            (Vsync::On, PresentMode::Relaxed) => 2,
+            (Vsync::On, PresentMode::Mailbox) => 1,
+            (Vsync::On, PresentMode::Immediate) => 0,
+            (Vsync::Off, PresentMode::Immediate) => 3,
+            (Vsync::Off, PresentMode::Mailbox) => 2,
+            (Vsync::Off, PresentMode::Relaxed) => 1,
+            (Vsync::Off, PresentMode::Fifo) => 0,
+        }
+    }
+
+    /// Get intent the present mode satisfies.
+    /// `Immediate` is the only mode that doesn't wait for vertical blank at all.
+    pub fn from_present_mode(present_mode: PresentMode) -> Self {
+        match present_mode {
+            PresentMode::Immediate => Vsync::Off,
+            PresentMode::Mailbox | PresentMode::Fifo | PresentMode::Relaxed => Vsync::On,
+        }
+    }
+
+    /// Pick present mode with highest priority from supported ones.
+    /// Returns `None` if `present_modes` is empty.
+    pub fn pick(&self, present_modes: &[PresentMode]) -> Option<PresentMode> {
+        present_modes
+            .iter()
+            .cloned()
+            .max_by_key(|&mode| self.priority(mode))
+    }
+}
+
+/// Color space of the swapchain images.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ColorSpace {
+    /// sRGB color space with non-linear transfer function.
+    Srgb,
+
+    /// scRGB color space. sRGB primaries with linear transfer function
+    /// and values outside of `0.0 ..= 1.0` range allowed.
+    /// Used with `Rgba16Sfloat` format for HDR on Windows.
+    ///
+    /// Value of `1.0` corresponds to 80 nits (sRGB reference white).
+    /// Negative values encode colors outside of sRGB gamut.
+    ///
+    /// Never negotiated, see [backend limitations](index.html#backend-limitations).
+    ExtendedSrgbLinear,
+}
+
+impl ColorSpace {
+    /// Check if color space is wide gamut or high dynamic range.
+    pub fn is_hdr(&self) -> bool {
+        match *self {
+            ColorSpace::Srgb => false,
+            ColorSpace::ExtendedSrgbLinear => true,
+        }
+    }
+}
+
+impl Default for ColorSpace {
+    fn default() -> Self {
+        ColorSpace::Srgb
+    }
+}
+
+/// HDR metadata of presented content.
+/// Mirrors fields of `VkHdrMetadataEXT`.
+///
+/// Chromaticity coordinates are in CIE 1931 xy space,
+/// luminance values are in nits.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HdrMetadata {
+    /// Red primary of the mastering display.
+    pub display_primary_red: [f32; 2],
+
+    /// Green primary of the mastering display.
+    pub display_primary_green: [f32; 2],
+
+    /// Blue primary of the mastering display.
+    pub display_primary_blue: [f32; 2],
+
+    /// White point of the mastering display.
+    pub white_point: [f32; 2],
+
+    /// Maximal luminance of the mastering display.
+    pub max_luminance: f32,
+
+    /// Minimal luminance of the mastering display.
+    pub min_luminance: f32,
+
+    /// Maximal content light level (MaxCLL).
+    pub max_content_light_level: f32,
+
+    /// Maximal frame average light level (MaxFALL).
+    pub max_frame_average_light_level: f32,
+}
+
+/// Swapchain parameters.
+/// Validated against surface capabilities when swapchain is created.
+///
+/// With `serde-1` feature enabled config can be serialized
+/// to persist user's graphics settings.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TargetConfig {
+    /// Extent of the swapchain images.
+    /// Used only if surface doesn't report its current extent.
+    pub extent: Extent2D,
+
+    /// Number of images in the swapchain.
+    /// Ignored if `image_count_policy` is `ImageCountPolicy::Auto`.
+    pub image_count: u32,
+
+    /// How number of images in the swapchain is picked.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub image_count_policy: ImageCountPolicy,
+
+    /// Present mode of the swapchain.
+    ///
+    /// `Mailbox` with less than 3 images is handled according to `mailbox_image_count`.
+    pub present_mode: PresentMode,
+
+    /// What to do if `Mailbox` present mode is requested with less than 3 images.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub mailbox_image_count: MailboxImageCount,
+
+    /// Usage of the swapchain images.
+    pub usage: Usage,
+
+    /// Scaling applied by presentation engine
+    /// when swapchain extent differs from surface extent.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub scaling: ScalingMode,
+
+    /// Use first format reported by the surface instead of picking best one.
+    /// Useful when format preference is already expressed by surface format order.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub use_first_format: bool,
+
+    /// Allow picking compressed formats.
+    /// Compressed formats are scored below uncompressed ones otherwise.
+    /// Picked compressed format must support color attachment usage.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub allow_compressed_formats: bool,
+
+    /// Preferred channel order of the swapchain format.
+    /// Used to pick between formats that are otherwise equally good.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub channel_order: ChannelOrder,
+
+    /// Formats to pick from in priority order.
+    /// First one supported by the surface is picked.
+    /// List ends at first `None`.
+    ///
+    /// Overrides `use_first_format` and `channel_order`.
+    /// If none of the formats is supported, format is picked as if the list is empty.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub format_priority: [Option<Format>; MAX_FORMAT_PRIORITY],
+
+    /// Class the swapchain format must belong to.
+    /// Swapchain creation fails if surface supports no formats of the class.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub required_format_class: FormatClass,
+
+    /// Number of semaphores in the pool used by `Target::acquire_pooled`.
+    /// `None` means one more than number of swapchain images.
+    ///
+    /// Must be larger than `image_count` as each acquired image keeps its semaphore
+    /// until it is acquired again, and one spare is needed to acquire next image.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub acquire_semaphore_count: Option<u32>,
+
+    /// Memory usage hint for the swapchain images.
+    /// Ignored as swapchain images are allocated by presentation engine,
+    /// see [backend limitations].
+    ///
+    /// [backend limitations]: index.html#backend-limitations
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub image_memory_hint: Option<MemoryUsageValue>,
+
+    /// Log complete surface capabilities, formats and present modes
+    /// at trace level each time swapchain is created.
+    /// Only picked parameters are logged otherwise.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub log_full_capabilities: bool,
+}
+
+impl TargetConfig {
+    /// Create new swapchain config.
+    pub fn new(
+        extent: Extent2D,
+        image_count: u32,
+        present_mode: PresentMode,
+        usage: Usage,
+    ) -> Self {
+        TargetConfig {
+            extent,
+            image_count,
+            image_count_policy: ImageCountPolicy::default(),
+            present_mode,
+            mailbox_image_count: MailboxImageCount::default(),
+            usage,
+            scaling: ScalingMode::default(),
+            use_first_format: false,
+            allow_compressed_formats: false,
+            channel_order: ChannelOrder::default(),
+            format_priority: [None; MAX_FORMAT_PRIORITY],
+            required_format_class: FormatClass::default(),
+            acquire_semaphore_count: None,
+            image_memory_hint: None,
+            log_full_capabilities: false,
+        }
+    }
+
+    /// Set extent of the swapchain images.
+    pub fn with_extent(mut self, extent: Extent2D) -> Self {
+        self.extent = extent;
+        self
+    }
+
+    /// Set number of images in the swapchain.
+    pub fn with_image_count(mut self, image_count: u32) -> Self {
+        self.image_count = image_count;
+        self
+    }
+
+    /// Set how number of images in the swapchain is picked.
+    pub fn with_image_count_policy(mut self, image_count_policy: ImageCountPolicy) -> Self {
+        self.image_count_policy = image_count_policy;
+        self
+    }
+
+    /// Set present mode of the swapchain.
+    pub fn with_present_mode(mut self, present_mode: PresentMode) -> Self {
+        self.present_mode = present_mode;
+        self
+    }
+
+    /// Set what to do if `Mailbox` present mode is requested with less than 3 images.
+    pub fn with_mailbox_image_count(mut self, mailbox_image_count: MailboxImageCount) -> Self {
+        self.mailbox_image_count = mailbox_image_count;
+        self
+    }
+
+    /// Set usage of the swapchain images.
+    pub fn with_usage(mut self, usage: Usage) -> Self {
+        self.usage = usage;
+        self
+    }
+
+    /// Add `TRANSFER_DST` to usage of the swapchain images.
+    /// Required by `Target::clear_image` and `Target::blit_from`.
+    pub fn with_transfer_dst(mut self) -> Self {
+        self.usage |= Usage::TRANSFER_DST;
+        self
+    }
+
+    /// Add `TRANSFER_SRC` to usage of the swapchain images.
+    /// Required to read back swapchain images, e.g. for screenshots.
+    /// Swapchain creation fails if the surface doesn't support it.
+    pub fn with_transfer_src(mut self) -> Self {
+        self.usage |= Usage::TRANSFER_SRC;
+        self
+    }
+
+    /// Set scaling mode of the swapchain.
+    pub fn with_scaling(mut self, scaling: ScalingMode) -> Self {
+        self.scaling = scaling;
+        self
+    }
+
+    /// Set whether first format reported by the surface is used as is.
+    /// By default formats are scored preferring uncompressed sRGB formats with more bits.
+    pub fn with_use_first_format(mut self, use_first_format: bool) -> Self {
+        self.use_first_format = use_first_format;
+        self
+    }
+
+    /// Set whether compressed formats are scored on par with uncompressed ones.
+    pub fn with_allow_compressed_formats(mut self, allow_compressed_formats: bool) -> Self {
+        self.allow_compressed_formats = allow_compressed_formats;
+        self
+    }
+
+    /// Set preferred channel order of the swapchain format.
+    pub fn with_channel_order(mut self, channel_order: ChannelOrder) -> Self {
+        self.channel_order = channel_order;
+        self
+    }
+
+    /// Set formats to pick from in priority order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if more than [`MAX_FORMAT_PRIORITY`] formats are specified.
+    ///
+    /// [`MAX_FORMAT_PRIORITY`]: constant.MAX_FORMAT_PRIORITY.html
+    pub fn with_format_priority(mut self, formats: &[Format]) -> Self {
+        assert!(
+            formats.len() <= MAX_FORMAT_PRIORITY,
+            "At most {} formats can be specified",
+            MAX_FORMAT_PRIORITY
+        );
+        self.format_priority = [None; MAX_FORMAT_PRIORITY];
+        for (slot, &format) in self.format_priority.iter_mut().zip(formats) {
+            *slot = Some(format);
+        }
+        self
+    }
+
+    /// Get formats to pick from in priority order.
+    pub fn format_priority(&self) -> impl Iterator<Item = Format> + '_ {
+        self.format_priority
+            .iter()
+            .take_while(|format| format.is_some())
+            .filter_map(|format| *format)
+    }
+
+    /// Set class the swapchain format must belong to.
+    pub fn with_required_format_class(mut self, class: FormatClass) -> Self {
+        self.required_format_class = class;
+        self
+    }
+
+    /// Set number of semaphores in the pool used by `Target::acquire_pooled`.
+    pub fn with_acquire_semaphore_count(mut self, count: u32) -> Self {
+        self.acquire_semaphore_count = Some(count);
+        self
+    }
+
+    /// Set memory usage hint for the swapchain images.
+    /// See [`image_memory_hint`].
+    ///
+    /// [`image_memory_hint`]: #structfield.image_memory_hint
+    pub fn with_image_memory_hint(mut self, usage: MemoryUsageValue) -> Self {
+        self.image_memory_hint = Some(usage);
+        self
+    }
+
+    /// Set whether complete surface capabilities are logged when swapchain is created.
+    pub fn with_log_full_capabilities(mut self, log_full_capabilities: bool) -> Self {
+        self.log_full_capabilities = log_full_capabilities;
+        self
+    }
+}
+
+/// Order of color channels in the swapchain format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ChannelOrder {
+    /// Red, green, blue, alpha.
+    Rgba,
+
+    /// Blue, green, red, alpha.
+    Bgra,
+
+    /// No preference.
+    DontCare,
+}
+
+impl Default for ChannelOrder {
+    fn default() -> Self {
+        ChannelOrder::DontCare
+    }
+}
+
+impl ChannelOrder {
+    /// Get channel order of the format.
+    /// Returns `None` for formats that are neither RGBA nor BGRA.
+    pub fn from_format(format: Format) -> Option<Self> {
+        match format.base_format().0 {
+            SurfaceType::R8_G8_B8_A8
+            | SurfaceType::R16_G16_B16_A16
+            | SurfaceType::R32_G32_B32_A32 => Some(ChannelOrder::Rgba),
+            SurfaceType::B8_G8_R8_A8 => Some(ChannelOrder::Bgra),
+            _ => None,
+        }
+    }
+}
+
+/// Class of swapchain formats.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FormatClass {
+    /// Any format.
+    Any,
+
+    /// RGBA or BGRA format with 8-bit unorm or sRGB channels.
+    Color8,
+}
+
+impl Default for FormatClass {
+    fn default() -> Self {
+        FormatClass::Any
+    }
+}
+
+impl FormatClass {
+    /// Check if format belongs to the class.
+    pub fn contains(&self, format: Format) -> bool {
+        match *self {
+            FormatClass::Any => true,
+            FormatClass::Color8 => match format.base_format() {
+                (SurfaceType::R8_G8_B8_A8, ChannelType::Unorm)
+                | (SurfaceType::R8_G8_B8_A8, ChannelType::Srgb)
+                | (SurfaceType::B8_G8_R8_A8, ChannelType::Unorm)
+                | (SurfaceType::B8_G8_R8_A8, ChannelType::Srgb) => true,
+                _ => false,
+            },
+        }
+    }
+}
+
+/// How number of swapchain images is picked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ImageCountPolicy {
+    /// Use `image_count` of the config.
+    Exact,
+
+    /// Derive number of images from present mode.
+    /// 3 images for `Mailbox` and 2 for other modes,
+    /// clamped to number of images supported by the surface.
+    Auto,
+}
+
+impl Default for ImageCountPolicy {
+    fn default() -> Self {
+        ImageCountPolicy::Exact
+    }
+}
+
+/// Handling of `Mailbox` present mode requested with less than 3 images.
+///
+/// `Mailbox` needs at least one spare image besides presented and queued ones.
+/// With fewer images it degenerates into `Fifo` with worse latency.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MailboxImageCount {
+    /// Use `Fifo` present mode instead.
+    FallbackToFifo,
+
+    /// Use 3 images if the surface supports that many.
+    /// Falls back to `Fifo` otherwise.
+    Bump,
+
+    /// Fail swapchain creation.
+    Error,
+}
+
+impl Default for MailboxImageCount {
+    fn default() -> Self {
+        MailboxImageCount::FallbackToFifo
+    }
+}
+
+impl ImageCountPolicy {
+    /// Get number of images for the present mode
+    /// within range of image counts supported by the surface.
+    pub fn image_count(
+        &self,
+        requested: u32,
+        present_mode: PresentMode,
+        supported: std::ops::Range<u32>,
+    ) -> u32 {
+        match *self {
+            ImageCountPolicy::Exact => requested,
+            ImageCountPolicy::Auto => {
+                let preferred = match present_mode {
+                    PresentMode::Mailbox => 3,
+                    PresentMode::Fifo | PresentMode::Relaxed | PresentMode::Immediate => 2,
+                };
+                preferred.max(supported.start).min(supported.end)
+            }
+        }
+    }
+}
+
+/// How presentation engine scales swapchain images
+/// when their extent differs from surface extent.
+///
+/// Modes other than `Stretch` are ignored, see [backend limitations].
+///
+/// [backend limitations]: index.html#backend-limitations
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ScalingMode {
+    /// Stretch image to fill whole surface.
+    Stretch,
+
+    /// Place image at the center of the surface without scaling.
+    Centered,
+
+    /// Scale image preserving its aspect ratio.
+    AspectRatioStretch,
+}
+
+impl Default for ScalingMode {
+    fn default() -> Self {
+        ScalingMode::Stretch
+    }
+}
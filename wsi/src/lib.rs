@@ -1,4 +1,15 @@
 //! Window system integration.
+//!
+//! # Backend limitations
+//!
+//! gfx-hal 0.2 doesn't expose color spaces, pre-transforms, scaling modes
+//! or tiling of swapchain images, nor timeline semaphores.
+//! Swapchain images are presented in sRGB non-linear color space with identity transform,
+//! and presentation engines allocate them with optimal tiling.
+//! Application can't allocate swapchain images itself.
+//! Neither gfx-hal 0.2 nor winit 0.19 report refresh rate of the display.
+//! HDR metadata can't be passed to the presentation engine.
+//! Surface queries can't fail and report no separate timeout error.
 
 #![warn(
     missing_debug_implementations,
@@ -11,6 +22,15 @@
     unused_qualifications
 )]
 
+pub use crate::{adapter::*, config::*, window::*};
+
+mod adapter;
+mod config;
+mod window;
+
+#[cfg(any(test, feature = "test-backend"))]
+pub mod test_backend;
+
 use {
     gfx_hal::{window::Extent2D, Backend, Device as _},
     rendy_resource::{Image, ImageInfo},
@@ -72,27 +92,110 @@ rendy_with_vulkan_backend! {
         ) -> <rendy_util::vulkan::Backend as gfx_hal::Backend>::Surface {
             instance.create_surface(window)
         }
+
+        #[cfg(all(
+            feature = "winit",
+            any(
+                target_os = "linux",
+                target_os = "dragonfly",
+                target_os = "freebsd",
+                target_os = "netbsd",
+                target_os = "openbsd"
+            )
+        ))]
+        // Some of winit handle types match ones taken by the backend and some don't,
+        // depending on platform and library versions, so casts may be trivial.
+        #[allow(trivial_casts, trivial_numeric_casts)]
+        pub(super) fn create_surface_with_protocol(
+            instance: &rendy_util::vulkan::Instance,
+            window: &winit::Window,
+            protocol: crate::DisplayProtocol,
+        ) -> Result<<rendy_util::vulkan::Backend as gfx_hal::Backend>::Surface, failure::Error> {
+            use {crate::DisplayProtocol, winit::os::unix::WindowExt};
+
+            let missing =
+                || failure::format_err!("Window doesn't use {:?} display protocol", protocol);
+
+            match protocol {
+                DisplayProtocol::Auto => Ok(instance.create_surface(window)),
+                DisplayProtocol::Xlib => {
+                    let display = window.get_xlib_display().ok_or_else(missing)?;
+                    let window = window.get_xlib_window().ok_or_else(missing)?;
+                    Ok(instance.create_surface_from_xlib(display as _, window as _))
+                }
+                DisplayProtocol::Xcb => {
+                    let connection = window.get_xcb_connection().ok_or_else(missing)?;
+                    let window = window.get_xlib_window().ok_or_else(missing)?;
+                    Ok(instance.create_surface_from_xcb(connection as _, window as _))
+                }
+                DisplayProtocol::Wayland => {
+                    let display = window.get_wayland_display().ok_or_else(missing)?;
+                    let surface = window.get_wayland_surface().ok_or_else(missing)?;
+                    Ok(instance.create_surface_from_wayland(display as _, surface as _))
+                }
+            }
+        }
     }
 }
 
+#[cfg(all(
+    feature = "winit",
+    any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    )
+))]
+#[allow(unused)]
+fn create_surface_with_protocol<B: Backend>(
+    instance: &Instance<B>,
+    window: &winit::Window,
+    protocol: DisplayProtocol,
+) -> Result<B::Surface, failure::Error> {
+    rendy_with_vulkan_backend!(if std::any::TypeId::of::<B>()
+        == std::any::TypeId::of::<rendy_util::vulkan::Backend>()
+    {
+        // We perform identical type transmute.
+        return gfx_backend_vulkan::create_surface_with_protocol(
+            instance.raw_typed().unwrap(),
+            window,
+            protocol,
+        )
+        .map(rendy_util::identical_cast);
+    });
+
+    failure::bail!("Display protocol can be chosen only with vulkan backend")
+}
+
 #[cfg(feature = "winit")]
 #[allow(unused)]
-fn create_surface<B: Backend>(instance: &Instance<B>, window: &winit::Window) -> B::Surface {
+fn create_surface<B: Backend>(
+    instance: &Instance<B>,
+    window: &winit::Window,
+) -> Result<B::Surface, failure::Error> {
     use rendy_util::identical_cast;
 
+    if backend_kind::<B>().is_none() {
+        failure::bail!(
+            "Undefined backend requested. Make sure feature for required backend is enabled"
+        );
+    }
+
     // We perform identical type transmute.
     rendy_backend_match!(B {
         empty => {
-            identical_cast(gfx_backend_empty::create_surface(instance.raw_typed().unwrap(), window))
+            Ok(identical_cast(gfx_backend_empty::create_surface(instance.raw_typed().unwrap(), window)))
         }
         dx12 => {
-            identical_cast(gfx_backend_dx12::create_surface(instance.raw_typed().unwrap(), window))
+            Ok(identical_cast(gfx_backend_dx12::create_surface(instance.raw_typed().unwrap(), window)))
         }
         metal => {
-            identical_cast(gfx_backend_metal::create_surface(instance.raw_typed().unwrap(), window))
+            Ok(identical_cast(gfx_backend_metal::create_surface(instance.raw_typed().unwrap(), window)))
         }
         vulkan => {
-            identical_cast(gfx_backend_vulkan::create_surface(instance.raw_typed().unwrap(), window))
+            Ok(identical_cast(gfx_backend_vulkan::create_surface(instance.raw_typed().unwrap(), window)))
         }
     })
 }
@@ -121,9 +224,68 @@ where
     B: Backend,
 {
     /// Create surface for the window.
-    #[cfg(feature = "winit")]
-    pub fn new(instance: &Instance<B>, window: &winit::Window) -> Self {
-        let raw = create_surface::<B>(instance, &window);
+    ///
+    /// # Panics
+    ///
+    /// Panics if backend `B` is not enabled in rendy.
+    /// See [`try_new`] for fallible version.
+    ///
+    /// [`try_new`]: #method.try_new
+    pub fn new(instance: &Instance<B>, window: &impl WindowHandle) -> Self {
+        Self::try_new(instance, window).unwrap()
+    }
+
+    /// Create surface for the window.
+    /// Fails if backend `B` is not enabled in rendy.
+    pub fn try_new(
+        instance: &Instance<B>,
+        window: &impl WindowHandle,
+    ) -> Result<Self, failure::Error> {
+        let raw = window.create_surface(instance)?;
+        Ok(Surface {
+            raw,
+            instance: instance.id(),
+        })
+    }
+
+    /// Create surface for the window using specified display protocol.
+    ///
+    /// Useful to force X11 or Wayland when automatic detection picks wrong one,
+    /// e.g. to work around XWayland issues.
+    /// Protocols other than `DisplayProtocol::Auto` are supported only by vulkan backend.
+    #[cfg(all(
+        feature = "winit",
+        any(
+            target_os = "linux",
+            target_os = "dragonfly",
+            target_os = "freebsd",
+            target_os = "netbsd",
+            target_os = "openbsd"
+        )
+    ))]
+    pub fn new_with_protocol(
+        instance: &Instance<B>,
+        window: &winit::Window,
+        protocol: DisplayProtocol,
+    ) -> Result<Self, failure::Error> {
+        if protocol == DisplayProtocol::Auto {
+            return Self::try_new(instance, window);
+        }
+
+        let raw = create_surface_with_protocol::<B>(instance, window, protocol)?;
+        Ok(Surface {
+            raw,
+            instance: instance.id(),
+        })
+    }
+
+    /// Wrap surface created from `instance` elsewhere,
+    /// e.g. with backend specific `Instance::create_surface_from_*` functions.
+    ///
+    /// # Safety
+    ///
+    /// `raw` must be created from raw instance of the `instance`.
+    pub unsafe fn from_raw(instance: &Instance<B>, raw: B::Surface) -> Self {
         Surface {
             raw,
             instance: instance.id(),
@@ -155,6 +317,47 @@ where
         &self.raw
     }
 
+    /// Destroy the surface.
+    ///
+    /// Backends destroy surface through the instance
+    /// when `B::Surface` is dropped, so dropping `Surface` doesn't leak.
+    /// This function additionally checks that surface is destroyed
+    /// while its instance is alive.
+    ///
+    /// Window is only borrowed by surface and stays valid after surface is destroyed.
+    /// This allows to switch backends at runtime keeping the same window:
+    ///
+    /// 1. Dispose `Target` and then `Surface` returned by it,
+    ///    before the old instance is dropped.
+    ///    Most platforms allow only one swapchain per window at a time,
+    ///    so surface must be destroyed before the new one is created.
+    /// 2. Create instance of another backend.
+    /// 3. Create new target for the same window, e.g. with `Target::auto`.
+    ///
+    /// ```no_run
+    /// # #[cfg(feature = "winit")]
+    /// # unsafe fn switch_backend<B: gfx_hal::Backend, N: gfx_hal::Backend>(
+    /// #     target: rendy_wsi::Target<B>,
+    /// #     device: rendy_util::Device<B>,
+    /// #     instance: rendy_util::Instance<B>,
+    /// #     new_instance: &rendy_util::Instance<N>,
+    /// #     window: &rendy_wsi::winit::Window,
+    /// # ) -> rendy_wsi::Surface<N> {
+    /// // Swapchain must not be in use anymore.
+    /// let surface = target.dispose(&device);
+    /// surface.dispose(&instance);
+    /// drop(device);
+    /// drop(instance);
+    ///
+    /// rendy_wsi::Surface::new(new_instance, window)
+    /// # }
+    /// ```
+    pub fn dispose(self, instance: &Instance<B>) {
+        self.assert_instance_owner(instance);
+        drop(self.raw);
+        log::trace!("Surface destroyed");
+    }
+
     /// Get current extent of the surface.
     pub unsafe fn extent(&self, physical_device: &B::PhysicalDevice) -> Option<Extent2D> {
         let (capabilities, _formats, _present_modes) = self.compatibility(physical_device);
@@ -165,20 +368,32 @@ where
     pub unsafe fn format(&self, physical_device: &B::PhysicalDevice) -> gfx_hal::format::Format {
         let (_capabilities, formats, _present_modes) =
             gfx_hal::Surface::compatibility(&self.raw, physical_device);
-        let formats = formats.unwrap();
+        pick_format(
+            formats.as_ref().map(Vec::as_slice),
+            false,
+            false,
+            ChannelOrder::DontCare,
+            &[],
+        )
+        .expect("At least one format must be supported by the surface")
+    }
 
-        *formats
-            .iter()
-            .max_by_key(|format| {
-                let base = format.base_format();
-                let desc = base.0.desc();
-                (
-                    !desc.is_compressed(),
-                    base.1 == gfx_hal::format::ChannelType::Srgb,
-                    desc.bits,
-                )
-            })
-            .expect("At least one format must be supported by the surface")
+    /// Get pairs of format and color space supported by the surface.
+    /// Returns `None` if surface supports any format.
+    ///
+    /// Each format is paired with `ColorSpace::Srgb`,
+    /// see [backend limitations](index.html#backend-limitations).
+    pub unsafe fn surface_format_color_space_pairs(
+        &self,
+        physical_device: &B::PhysicalDevice,
+    ) -> Option<Vec<(gfx_hal::format::Format, ColorSpace)>> {
+        let (_capabilities, formats, _present_modes) = self.compatibility(physical_device);
+        formats.map(|formats| {
+            formats
+                .into_iter()
+                .map(|format| (format, ColorSpace::Srgb))
+                .collect()
+        })
     }
 
     /// Get surface compatibility
@@ -197,8 +412,11 @@ where
     }
 
     /// Cast surface into render target.
+    /// See [`Target::from_surface`] to create target with full config.
+    ///
+    /// [`Target::from_surface`]: struct.Target.html#method.from_surface
     pub unsafe fn into_target(
-        mut self,
+        self,
         physical_device: &B::PhysicalDevice,
         device: &Device<B>,
         suggest_extent: Extent2D,
@@ -206,174 +424,1061 @@ where
         present_mode: gfx_hal::PresentMode,
         usage: gfx_hal::image::Usage,
     ) -> Result<Target<B>, failure::Error> {
-        assert_eq!(
-            device.id().instance,
-            self.instance,
-            "Resource is not owned by specified instance"
-        );
+        let config = TargetConfig::new(suggest_extent, image_count, present_mode, usage);
+        Target::from_surface(self, physical_device, device, config)
+    }
+}
 
-        let (swapchain, backbuffer, extent) = create_swapchain(
-            &mut self,
-            physical_device,
-            device,
-            suggest_extent,
-            image_count,
-            present_mode,
-            usage,
-        )?;
+/// Everything surface reports about its support for particular physical device.
+///
+/// `color_spaces` always contains `ColorSpace::Srgb` only
+/// and transforms are not included, see [backend limitations].
+///
+/// [backend limitations]: index.html#backend-limitations
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SurfaceSupport {
+    /// Supported present modes.
+    pub present_modes: Vec<gfx_hal::PresentMode>,
 
-        Ok(Target {
-            device: device.id(),
-            relevant: relevant::Relevant,
-            surface: self,
-            swapchain: Some(swapchain),
-            backbuffer: Some(backbuffer),
-            extent,
-            present_mode,
-            usage,
-        })
-    }
+    /// Supported formats.
+    /// `None` means that any format is supported.
+    pub formats: Option<Vec<gfx_hal::format::Format>>,
+
+    /// Supported color spaces.
+    pub color_spaces: Vec<ColorSpace>,
+
+    /// Minimal number of swapchain images.
+    pub min_images: u32,
+
+    /// Maximal number of swapchain images.
+    pub max_images: u32,
+
+    /// Maximal number of image layers.
+    pub max_layers: gfx_hal::image::Layer,
+
+    /// Current extent of the surface if surface reports it.
+    pub current_extent: Option<Extent2D>,
+
+    /// Minimal extent of swapchain images.
+    pub min_extent: Extent2D,
+
+    /// Maximal extent of swapchain images.
+    pub max_extent: Extent2D,
+
+    /// Supported usage of swapchain images.
+    pub usage: gfx_hal::image::Usage,
+
+    /// Supported composite alpha modes.
+    pub composite_alpha: gfx_hal::window::CompositeAlpha,
 }
 
-unsafe fn create_swapchain<B: Backend>(
-    surface: &mut Surface<B>,
+/// Get everything `surface` supports for `physical_device` at once.
+///
+/// # Safety
+///
+/// `physical_device` must be created from same `Instance` as the `surface`.
+pub unsafe fn surface_support<B: Backend>(
+    surface: &Surface<B>,
     physical_device: &B::PhysicalDevice,
-    device: &Device<B>,
-    suggest_extent: Extent2D,
-    image_count: u32,
-    present_mode: gfx_hal::PresentMode,
-    usage: gfx_hal::image::Usage,
-) -> Result<(B::Swapchain, Vec<Image<B>>, Extent2D), failure::Error> {
+) -> SurfaceSupport {
     let (capabilities, formats, present_modes) = surface.compatibility(physical_device);
+    let image_count = image_count_range(&capabilities);
 
-    if !present_modes.contains(&present_mode) {
-        log::warn!(
-            "Present mode is not supported. Supported: {:#?}, requested: {:#?}",
-            present_modes,
-            present_mode,
-        );
-        failure::bail!("Present mode not supported.");
+    SurfaceSupport {
+        present_modes,
+        formats,
+        color_spaces: vec![ColorSpace::Srgb],
+        min_images: image_count.start,
+        max_images: image_count.end,
+        max_layers: capabilities.max_image_layers,
+        current_extent: capabilities.current_extent,
+        min_extent: *capabilities.extents.start(),
+        max_extent: *capabilities.extents.end(),
+        usage: capabilities.usage,
+        composite_alpha: capabilities.composite_alpha,
     }
+}
 
-    log::trace!(
-        "Surface present modes: {:#?}. Pick {:#?}",
-        present_modes,
-        present_mode
-    );
+/// Get composite alpha modes `surface` supports for `physical_device`.
+///
+/// Transparent windows require any mode other than `OPAQUE`.
+///
+/// # Safety
+///
+/// `physical_device` must be created from same `Instance` as the `surface`.
+pub unsafe fn surface_composite_alpha<B: Backend>(
+    surface: &Surface<B>,
+    physical_device: &B::PhysicalDevice,
+) -> gfx_hal::window::CompositeAlpha {
+    let (capabilities, _formats, _present_modes) = surface.compatibility(physical_device);
+    capabilities.composite_alpha
+}
+
+/// Format used when surface supports any format.
+const DEFAULT_FORMAT: gfx_hal::format::Format = gfx_hal::format::Format::Rgba8Srgb;
 
-    let formats = formats.unwrap();
+/// Pick format from formats supported by the surface.
+/// `None` means that any format is supported.
+/// Compressed formats are picked only if nothing else is supported
+/// unless `allow_compressed` is set.
+/// `channel_order` breaks ties between otherwise equal formats.
+/// Returns `None` if surface reports empty list of formats.
+fn pick_format(
+    formats: Option<&[gfx_hal::format::Format]>,
+    use_first: bool,
+    allow_compressed: bool,
+    channel_order: ChannelOrder,
+    priority: &[gfx_hal::format::Format],
+) -> Option<gfx_hal::format::Format> {
+    let formats = match formats {
+        Some(formats) => {
+            if let Some(format) = priority.iter().find(|format| formats.contains(format)) {
+                return Some(*format);
+            }
+            formats
+        }
+        None if !priority.is_empty() => return Some(priority[0]),
+        None if channel_order == ChannelOrder::Bgra => {
+            return Some(gfx_hal::format::Format::Bgra8Srgb)
+        }
+        None => return Some(DEFAULT_FORMAT),
+    };
+
+    if use_first {
+        return formats.first().cloned();
+    }
 
-    let format = *formats
+    formats
         .iter()
         .max_by_key(|format| {
             let base = format.base_format();
             let desc = base.0.desc();
             (
-                !desc.is_compressed(),
+                allow_compressed || !desc.is_compressed(),
                 base.1 == gfx_hal::format::ChannelType::Srgb,
                 desc.bits,
+                channel_order != ChannelOrder::DontCare
+                    && ChannelOrder::from_format(**format) == Some(channel_order),
             )
         })
-        .unwrap();
+        .cloned()
+}
+
+/// Map `AcquireError::NotReady` to `Ok(None)`.
+fn not_ready_as_none<T>(
+    result: Result<T, gfx_hal::AcquireError>,
+) -> Result<Option<T>, gfx_hal::AcquireError> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(gfx_hal::AcquireError::NotReady) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Get range of swapchain image counts supported by the surface.
+///
+/// Some drivers erroneously report zero as minimal image count.
+/// Minimal count is clamped to one in that case.
+pub fn image_count_range(
+    capabilities: &gfx_hal::window::SurfaceCapabilities,
+) -> std::ops::Range<u32> {
+    let mut range = capabilities.image_count.clone();
+    if range.start == 0 {
+        log::warn!("Surface reports zero minimal image count. Using one instead");
+        range.start = 1;
+    }
+    range
+}
+
+/// Replace present mode of the `config` if it is not supported anymore.
+/// Replacement is picked according to `Vsync` intent of the original mode.
+fn fallback_present_mode(
+    config: TargetConfig,
+    present_modes: &[gfx_hal::PresentMode],
+) -> Result<TargetConfig, failure::Error> {
+    if present_modes.contains(&config.present_mode) {
+        return Ok(config);
+    }
+
+    let vsync = Vsync::from_present_mode(config.present_mode);
+    match vsync.pick(present_modes) {
+        Some(present_mode) => {
+            log::debug!(
+                "Present mode {:?} is no longer supported. Fallback to {:?}",
+                config.present_mode,
+                present_mode
+            );
+            Ok(config.with_present_mode(present_mode))
+        }
+        None => failure::bail!("Surface supports no present modes"),
+    }
+}
+
+/// Replace present modes that don't wait for vertical blank with `Fifo` when on battery.
+fn power_saving_present_mode(
+    config: TargetConfig,
+    present_modes: &[gfx_hal::PresentMode],
+    on_battery: bool,
+) -> TargetConfig {
+    match config.present_mode {
+        gfx_hal::PresentMode::Mailbox | gfx_hal::PresentMode::Immediate
+            if on_battery && present_modes.contains(&gfx_hal::PresentMode::Fifo) =>
+        {
+            log::debug!(
+                "Running on battery. Present mode {:?} is replaced with Fifo",
+                config.present_mode
+            );
+            config.with_present_mode(gfx_hal::PresentMode::Fifo)
+        }
+        _ => config,
+    }
+}
+
+/// Get present mode swapchain is actually created with.
+fn effective_present_mode(
+    present_mode: gfx_hal::PresentMode,
+    image_count: u32,
+    present_modes: &[gfx_hal::PresentMode],
+) -> gfx_hal::PresentMode {
+    // Mailbox needs at least one spare image besides presented and queued ones.
+    if present_mode == gfx_hal::PresentMode::Mailbox
+        && image_count < 3
+        && present_modes.contains(&gfx_hal::PresentMode::Fifo)
+    {
+        log::debug!(
+            "Mailbox present mode requires at least 3 images, {} requested. Fallback to Fifo",
+            image_count
+        );
+        gfx_hal::PresentMode::Fifo
+    } else {
+        present_mode
+    }
+}
+
+/// Get extent of the swapchain images.
+/// Surface current extent takes precedence over suggested one.
+/// Suggested extent is clamped to extents supported by the surface.
+fn swapchain_extent(
+    capabilities: &gfx_hal::window::SurfaceCapabilities,
+    suggest_extent: Extent2D,
+) -> Extent2D {
+    capabilities
+        .current_extent
+        .unwrap_or_else(|| clamp_to_caps(suggest_extent, capabilities))
+}
+
+/// Clamp `extent` to extents supported by the surface.
+///
+/// Window size may briefly go out of supported range, e.g. when window is maximized.
+/// Non-zero dimension is never clamped to zero,
+/// even if surface erroneously reports zero as minimal extent.
+fn clamp_to_caps(
+    extent: Extent2D,
+    capabilities: &gfx_hal::window::SurfaceCapabilities,
+) -> Extent2D {
+    let min = capabilities.extents.start();
+    let max = capabilities.extents.end();
+
+    let clamp = |value: u32, min: u32, max: u32| {
+        let clamped = value.max(min).min(max);
+        if value > 0 {
+            clamped.max(1)
+        } else {
+            clamped
+        }
+    };
+
+    Extent2D {
+        width: clamp(extent.width, min.width, max.width),
+        height: clamp(extent.height, min.height, max.height),
+    }
+}
+
+/// Get number of swapchain images according to image count policy of the `config`.
+fn swapchain_image_count(
+    capabilities: &gfx_hal::window::SurfaceCapabilities,
+    config: &TargetConfig,
+) -> u32 {
+    let supported = image_count_range(capabilities);
+    let image_count = config.image_count_policy.image_count(
+        config.image_count,
+        config.present_mode,
+        supported.clone(),
+    );
+
+    if config.present_mode == gfx_hal::PresentMode::Mailbox
+        && config.mailbox_image_count == MailboxImageCount::Bump
+        && image_count < 3
+        && supported.end >= 3
+    {
+        log::warn!(
+            "Mailbox present mode requires at least 3 images, {} requested. Using 3 images",
+            image_count
+        );
+        3
+    } else {
+        image_count
+    }
+}
 
-    log::trace!("Surface formats: {:#?}. Pick {:#?}", formats, format);
+/// Negotiate swapchain parameters for `config` with the surface
+/// with specified capabilities, formats and present modes.
+///
+/// Every check that may reject `config` is made here,
+/// so that nothing is torn down before `config` is known to be supported.
+/// `supports_color_attachment` reports whether compressed format can be rendered to.
+fn validate_config(
+    capabilities: &gfx_hal::window::SurfaceCapabilities,
+    formats: Option<&[gfx_hal::format::Format]>,
+    present_modes: &[gfx_hal::PresentMode],
+    config: &TargetConfig,
+    supports_color_attachment: impl Fn(gfx_hal::format::Format) -> bool,
+) -> Result<gfx_hal::SwapchainConfig, failure::Error> {
+    if !present_modes.contains(&config.present_mode) {
+        log::warn!(
+            "Present mode is not supported. Supported: {:#?}, requested: {:#?}",
+            present_modes,
+            config.present_mode,
+        );
+        failure::bail!("Present mode not supported.");
+    }
 
-    if image_count < capabilities.image_count.start || image_count > capabilities.image_count.end {
+    let supported = image_count_range(capabilities);
+    let image_count = swapchain_image_count(capabilities, config);
+    if image_count < supported.start || image_count > supported.end {
         log::warn!(
             "Image count not supported. Supported: {:#?}, requested: {:#?}",
-            capabilities.image_count,
+            supported,
             image_count
         );
         failure::bail!("Image count not supported.")
     }
 
-    log::trace!(
-        "Surface capabilities: {:#?}. Pick {} images",
-        capabilities.image_count,
-        image_count
-    );
+    if config.present_mode == gfx_hal::PresentMode::Mailbox
+        && config.mailbox_image_count == MailboxImageCount::Error
+        && image_count < 3
+    {
+        log::warn!(
+            "Mailbox present mode requires at least 3 images, {} requested",
+            image_count
+        );
+        failure::bail!("Too few images for Mailbox present mode.")
+    }
 
-    assert!(
-        capabilities.usage.contains(usage),
-        "Surface supports {:?}, but {:?} was requested"
-    );
+    if let Some(count) = config.acquire_semaphore_count {
+        if count <= image_count {
+            log::warn!(
+                "Acquire semaphore pool must be larger than image count. Image count: {}, requested: {}",
+                image_count,
+                count
+            );
+            failure::bail!("Acquire semaphore pool is too small.")
+        }
+    }
 
-    let extent = capabilities.current_extent.unwrap_or(suggest_extent);
+    if !capabilities.usage.contains(config.usage) {
+        log::warn!(
+            "Image usage not supported. Supported: {:?}, requested: {:?}",
+            capabilities.usage,
+            config.usage
+        );
+        failure::bail!("Image usage not supported.")
+    }
 
-    let (swapchain, images) = device.create_swapchain(
-        &mut surface.raw,
-        gfx_hal::SwapchainConfig {
-            present_mode,
-            format,
-            extent,
+    if config.image_count_policy == ImageCountPolicy::Auto {
+        log::debug!(
+            "Picked {} images for {:?} present mode",
             image_count,
-            image_layers: 1,
-            image_usage: usage,
-            composite_alpha: [
-                gfx_hal::window::CompositeAlpha::INHERIT,
-                gfx_hal::window::CompositeAlpha::OPAQUE,
-                gfx_hal::window::CompositeAlpha::PREMULTIPLIED,
-                gfx_hal::window::CompositeAlpha::POSTMULTIPLIED,
-            ]
+            config.present_mode
+        );
+    }
+
+    let TargetConfig {
+        extent: suggest_extent,
+        image_count: _,
+        image_count_policy: _,
+        present_mode,
+        mailbox_image_count: _,
+        usage,
+        scaling,
+        use_first_format,
+        allow_compressed_formats,
+        channel_order,
+        format_priority: _,
+        required_format_class,
+        acquire_semaphore_count: _,
+        image_memory_hint,
+        log_full_capabilities: _,
+    } = *config;
+
+    let present_mode = effective_present_mode(present_mode, image_count, present_modes);
+
+    if let Some(image_memory_hint) = image_memory_hint {
+        log::debug!(
+            "Swapchain images are allocated by presentation engine. Memory hint {:?} is ignored",
+            image_memory_hint
+        );
+    }
+
+    if scaling != ScalingMode::Stretch {
+        log::debug!(
+            "Scaling mode {:?} is not supported by gfx-hal 0.2 swapchain. Images will be stretched",
+            scaling
+        );
+    }
+
+    let format_priority: smallvec::SmallVec<[_; MAX_FORMAT_PRIORITY]> =
+        config.format_priority().collect();
+    let class_formats: Option<Vec<_>> = formats.map(|formats| {
+        formats
             .iter()
-            .find(|&bit| capabilities.composite_alpha & *bit == *bit)
             .cloned()
-            .expect("No CompositeAlpha modes supported"),
+            .filter(|&format| required_format_class.contains(format))
+            .collect()
+    });
+    let format = match pick_format(
+        class_formats.as_ref().map(Vec::as_slice),
+        use_first_format,
+        allow_compressed_formats,
+        channel_order,
+        &format_priority,
+    ) {
+        Some(format) if required_format_class.contains(format) => format,
+        _ if formats.map_or(false, <[_]>::is_empty) => {
+            failure::bail!("Surface supports no formats")
+        }
+        _ => {
+            log::warn!(
+                "No format of class {:?} is supported. Supported: {:#?}",
+                required_format_class,
+                formats
+            );
+            failure::bail!("No suitable format.")
+        }
+    };
+
+    if format.surface_desc().is_compressed() && !supports_color_attachment(format) {
+        log::warn!(
+            "Compressed format {:?} can't be used as color attachment",
+            format
+        );
+        failure::bail!("Compressed format not supported as color attachment.");
+    }
+
+    let composite_alpha = match [
+        gfx_hal::window::CompositeAlpha::INHERIT,
+        gfx_hal::window::CompositeAlpha::OPAQUE,
+        gfx_hal::window::CompositeAlpha::PREMULTIPLIED,
+        gfx_hal::window::CompositeAlpha::POSTMULTIPLIED,
+    ]
+    .iter()
+    .find(|&bit| capabilities.composite_alpha & *bit == *bit)
+    {
+        Some(composite_alpha) => *composite_alpha,
+        None => failure::bail!("Surface supports no composite alpha modes"),
+    };
+
+    log::trace!(
+        "Pick present mode {:?}, format {:?}, {} images",
+        present_mode,
+        format,
+        image_count
+    );
+
+    Ok(gfx_hal::SwapchainConfig {
+        present_mode,
+        format,
+        extent: swapchain_extent(capabilities, suggest_extent),
+        image_count,
+        image_layers: 1,
+        image_usage: usage,
+        composite_alpha,
+    })
+}
+
+/// Query surface support and negotiate swapchain parameters for `config`.
+///
+/// Present mode is downgraded to save power if `on_battery` is set.
+unsafe fn negotiate_swapchain<B: Backend>(
+    surface: &Surface<B>,
+    physical_device: &B::PhysicalDevice,
+    config: &TargetConfig,
+    on_battery: bool,
+) -> Result<(gfx_hal::SwapchainConfig, SwapchainSupport), failure::Error> {
+    let (capabilities, formats, present_modes) = surface.compatibility(physical_device);
+
+    if config.log_full_capabilities {
+        log::trace!(
+            "Surface capabilities: {:#?}\nFormats: {:#?}\nPresent modes: {:#?}",
+            capabilities,
+            formats,
+            present_modes
+        );
+    }
+
+    let config = power_saving_present_mode(*config, &present_modes, on_battery);
+    let swapchain_config = validate_config(
+        &capabilities,
+        formats.as_ref().map(Vec::as_slice),
+        &present_modes,
+        &config,
+        |format| {
+            gfx_hal::PhysicalDevice::format_properties(physical_device, Some(format))
+                .optimal_tiling
+                .contains(gfx_hal::format::ImageFeature::COLOR_ATTACHMENT)
         },
-        None,
     )?;
 
+    Ok((
+        swapchain_config,
+        SwapchainSupport {
+            formats,
+            present_modes,
+            usage: capabilities.usage,
+            composite_alpha: capabilities.composite_alpha,
+        },
+    ))
+}
+
+/// Create swapchain with parameters negotiated by `negotiate_swapchain`.
+unsafe fn create_swapchain<B: Backend>(
+    surface: &mut Surface<B>,
+    device: &Device<B>,
+    swapchain_config: &gfx_hal::SwapchainConfig,
+    old_swapchain: Option<B::Swapchain>,
+) -> Result<NewSwapchain<B>, failure::Error> {
+    let (swapchain, images) =
+        device.create_swapchain(&mut surface.raw, swapchain_config.clone(), old_swapchain)?;
+
+    // Tiling of swapchain images is not reported.
+    // Presentation engines allocate them with optimal tiling.
+    let tiling = gfx_hal::image::Tiling::Optimal;
+
     let backbuffer = images
         .into_iter()
         .map(|image| {
             Image::create_from_swapchain(
                 device.id(),
-                ImageInfo {
-                    kind: gfx_hal::image::Kind::D2(extent.width, extent.height, 1, 1),
-                    levels: 1,
-                    format,
-                    tiling: gfx_hal::image::Tiling::Optimal,
-                    view_caps: gfx_hal::image::ViewCapabilities::empty(),
-                    usage,
-                },
+                swapchain_image_info(swapchain_config, tiling),
                 image,
             )
         })
         .collect();
 
-    Ok((swapchain, backbuffer, extent))
+    Ok(NewSwapchain {
+        raw: swapchain,
+        backbuffer,
+        tiling,
+    })
 }
 
-/// Rendering target bound to window.
-/// With swapchain created.
-pub struct Target<B: Backend> {
-    device: DeviceId,
-    surface: Surface<B>,
-    swapchain: Option<B::Swapchain>,
-    backbuffer: Option<Vec<Image<B>>>,
-    extent: Extent2D,
-    present_mode: gfx_hal::PresentMode,
+/// Swapchain created by `create_swapchain`.
+struct NewSwapchain<B: Backend> {
+    raw: B::Swapchain,
+    backbuffer: Vec<Image<B>>,
+    tiling: gfx_hal::image::Tiling,
+}
+
+/// Surface capabilities that affect swapchain parameters negotiation.
+#[derive(Clone, Debug, PartialEq)]
+struct SwapchainSupport {
+    formats: Option<Vec<gfx_hal::format::Format>>,
+    present_modes: Vec<gfx_hal::PresentMode>,
     usage: gfx_hal::image::Usage,
-    relevant: relevant::Relevant,
+    composite_alpha: gfx_hal::window::CompositeAlpha,
 }
 
-device_owned!(Target<B>);
+/// Warn if sRGB format is requested but surface supports none.
+fn warn_no_srgb(
+    config: &TargetConfig,
+    support: &SwapchainSupport,
+    format: gfx_hal::format::Format,
+) {
+    let is_srgb = |format: &gfx_hal::format::Format| {
+        format.base_format().1 == gfx_hal::format::ChannelType::Srgb
+    };
 
-impl<B> std::fmt::Debug for Target<B>
-where
-    B: Backend,
-{
-    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        fmt.debug_struct("Target")
-            .field("backbuffer", &self.backbuffer)
-            .finish()
+    // `None` means any format is supported, so sRGB one is picked.
+    if let Some(formats) = &support.formats {
+        if !config.use_first_format && !formats.iter().any(is_srgb) {
+            log::warn!(
+                "Surface supports no sRGB formats. Picked {:?}, manual gamma correction is required",
+                format
+            );
+        }
     }
 }
 
-impl<B> Target<B>
-where
-    B: Backend,
-{
+/// Get info of the images of swapchain created with `config`.
+fn swapchain_image_info(
+    config: &gfx_hal::SwapchainConfig,
+    tiling: gfx_hal::image::Tiling,
+) -> ImageInfo {
+    ImageInfo {
+        kind: gfx_hal::image::Kind::D2(
+            config.extent.width,
+            config.extent.height,
+            config.image_layers,
+            1,
+        ),
+        levels: 1,
+        format: config.format,
+        tiling,
+        view_caps: gfx_hal::image::ViewCapabilities::empty(),
+        usage: config.image_usage,
+    }
+}
+
+/// Semaphores signaled by image acquisition.
+struct AcquireSemaphores<B: Backend> {
+    free: Vec<B::Semaphore>,
+    per_image: Vec<Option<B::Semaphore>>,
+}
+
+impl<B> AcquireSemaphores<B>
+where
+    B: Backend,
+{
+    /// Return all semaphores to the pool
+    /// and create or destroy semaphores to match `pool_size`.
+    fn reset(
+        &mut self,
+        device: &Device<B>,
+        image_count: u32,
+        pool_size: u32,
+    ) -> Result<(), gfx_hal::device::OutOfMemory> {
+        self.free.extend(self.per_image.drain(..).flatten());
+        self.per_image = (0..image_count).map(|_| None).collect();
+
+        while self.free.len() > pool_size as usize {
+            let semaphore = self.free.pop().unwrap();
+            unsafe { device.destroy_semaphore(semaphore) };
+        }
+        while self.free.len() < pool_size as usize {
+            self.free.push(device.create_semaphore()?);
+        }
+
+        Ok(())
+    }
+
+    unsafe fn dispose(self, device: &Device<B>) {
+        self.free
+            .into_iter()
+            .chain(self.per_image.into_iter().flatten())
+            .for_each(|semaphore| device.destroy_semaphore(semaphore));
+    }
+}
+
+/// Get size of the acquire semaphore pool for swapchain with `image_count` images.
+fn acquire_semaphore_pool_size(config: &TargetConfig, image_count: u32) -> u32 {
+    config.acquire_semaphore_count.unwrap_or(image_count + 1)
+}
+
+/// Create color view covering whole image for each of swapchain `images`.
+/// Views created before an error are destroyed.
+unsafe fn create_image_views<B: Backend>(
+    device: &Device<B>,
+    images: &[Image<B>],
+    config: &gfx_hal::SwapchainConfig,
+) -> Result<Vec<B::ImageView>, gfx_hal::image::ViewError> {
+    let mut views = Vec::with_capacity(images.len());
+    for image in images {
+        let view = device.create_image_view(
+            image.raw(),
+            gfx_hal::image::ViewKind::D2,
+            config.format,
+            gfx_hal::format::Swizzle::NO,
+            gfx_hal::image::SubresourceRange {
+                aspects: gfx_hal::format::Aspects::COLOR,
+                levels: 0..1,
+                layers: 0..config.image_layers,
+            },
+        );
+        match view {
+            Ok(view) => views.push(view),
+            Err(err) => {
+                views
+                    .into_iter()
+                    .for_each(|view| device.destroy_image_view(view));
+                return Err(err);
+            }
+        }
+    }
+    Ok(views)
+}
+
+/// Target created along with automatically picked adapter and opened device.
+#[derive(derivative::Derivative)]
+#[derivative(Debug)]
+pub struct AutoTarget<B: Backend> {
+    /// Created target.
+    pub target: Target<B>,
+
+    /// Device opened from picked adapter.
+    pub device: Device<B>,
+
+    /// Picked adapter.
+    #[derivative(Debug = "ignore")]
+    pub adapter: gfx_hal::Adapter<B>,
+
+    /// Family of the `queue`.
+    pub family: gfx_hal::queue::QueueFamilyId,
+
+    /// Queue that supports graphics and presentation to the target.
+    #[derivative(Debug = "ignore")]
+    pub queue: B::CommandQueue,
+}
+
+/// Target created along with opened device
+/// using one of adapters provided to [`Target::new_with_retry`].
+///
+/// [`Target::new_with_retry`]: struct.Target.html#method.new_with_retry
+#[derive(derivative::Derivative)]
+#[derivative(Debug)]
+pub struct AdapterTarget<B: Backend> {
+    /// Created target.
+    pub target: Target<B>,
+
+    /// Device opened from used adapter.
+    pub device: Device<B>,
+
+    /// Index of the used adapter in provided slice.
+    pub adapter_index: usize,
+
+    /// Family of the `queue`.
+    pub family: gfx_hal::queue::QueueFamilyId,
+
+    /// Queue that supports graphics and presentation to the target.
+    #[derivative(Debug = "ignore")]
+    pub queue: B::CommandQueue,
+}
+
+/// Rendering target bound to window.
+/// With swapchain created.
+pub struct Target<B: Backend> {
+    device: DeviceId,
+    surface: Surface<B>,
+    swapchain: Option<B::Swapchain>,
+    support: SwapchainSupport,
+    backbuffer: Option<Vec<Image<B>>>,
+    swapchain_config: gfx_hal::SwapchainConfig,
+    tiling: gfx_hal::image::Tiling,
+    config: TargetConfig,
+    images_in_flight: Vec<Option<B::Fence>>,
+    // Generation the fence is retired from, timeline value it is retired at and the fence.
+    pending_destroy: Vec<(u64, Option<u64>, B::Fence)>,
+    timeline_value: Option<u64>,
+    generation: u64,
+    frame_interval: Option<std::time::Duration>,
+    // Mutex keeps `Target` `Sync` while presentation updates it through shared reference.
+    last_present: std::sync::Mutex<Option<std::time::Instant>>,
+    last_acquired: Option<ImageIndex>,
+    last_presented: std::sync::Mutex<Option<ImageIndex>>,
+    before_present: Option<std::sync::Mutex<Box<dyn FnMut(ImageIndex) + Send>>>,
+    on_recreate: Option<std::sync::Mutex<Box<dyn FnMut(&RecreateOutcome) + Send>>>,
+    cpu_ahead_limit: Option<u64>,
+    mark_order: std::collections::VecDeque<u32>,
+    present_family: Option<gfx_hal::queue::QueueFamilyId>,
+    allow_non_graphics_present: bool,
+    is_on_battery: Option<Box<dyn Fn() -> bool + Send + Sync>>,
+    // Whether fence was marked for the image since it was acquired. `None` if validation is off.
+    guarded: Option<Vec<bool>>,
+    max_recreations: u32,
+    device_lost: bool,
+    render_finished: Option<Vec<B::Semaphore>>,
+    image_views: Option<Vec<B::ImageView>>,
+    acquire_semaphores: Option<AcquireSemaphores<B>>,
+    hdr_metadata: Option<HdrMetadata>,
+    acquire_wait_stage: gfx_hal::pso::PipelineStage,
+    #[cfg(feature = "winit")]
+    monitor: Option<winit::MonitorId>,
+    layouts: std::sync::Mutex<Vec<gfx_hal::image::Layout>>,
+    relevant: relevant::Relevant,
+}
+
+device_owned!(Target<B>);
+
+impl<B> std::fmt::Debug for Target<B>
+where
+    B: Backend,
+{
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt.debug_struct("Target")
+            .field("backbuffer", &self.backbuffer)
+            .finish()
+    }
+}
+
+impl<B> Target<B>
+where
+    B: Backend,
+{
+    /// Create target for the window picking best adapter that can present to it.
+    /// Opens device with single queue that supports graphics and presentation.
+    ///
+    /// Present mode is picked according to `Vsync::On`.
+    #[cfg(feature = "winit")]
+    pub fn auto(
+        instance: &Instance<B>,
+        window: &winit::Window,
+        image_count: u32,
+        usage: gfx_hal::image::Usage,
+    ) -> Result<AutoTarget<B>, failure::Error> {
+        let surface = Surface::try_new(instance, window)?;
+
+        let mut adapters = enumerate_surface_compatible_adapters(instance, &surface);
+        let picked = match pick_best(&adapters, AdapterPreference::default()) {
+            Some(picked) => picked,
+            None => failure::bail!("No adapter can present to the window"),
+        };
+        let CompatibleAdapter { adapter, family } = adapters.swap_remove(picked);
+
+        let (target, device, queue) = Self::open_with_adapter(
+            instance,
+            surface,
+            &adapter,
+            family,
+            window,
+            image_count,
+            usage,
+        )?;
+
+        Ok(AutoTarget {
+            target,
+            device,
+            adapter,
+            family,
+            queue,
+        })
+    }
+
+    /// Create target for the window like [`auto`]
+    /// but try each of `adapters` that can present to the window in turn
+    /// until target is created successfully.
+    ///
+    /// Adapters are tried in order of `AdapterPreference::default()`.
+    /// Failure to create surface, open device or create swapchain with one adapter
+    /// is logged and next adapter is tried.
+    /// Index of the adapter used is returned in `AdapterTarget`.
+    /// Fails with error of the last attempt if no adapter succeeds.
+    ///
+    /// [`auto`]: #method.auto
+    #[cfg(feature = "winit")]
+    pub fn new_with_retry(
+        instance: &Instance<B>,
+        adapters: &[gfx_hal::Adapter<B>],
+        window: &winit::Window,
+        image_count: u32,
+        usage: gfx_hal::image::Usage,
+    ) -> Result<AdapterTarget<B>, failure::Error> {
+        let preference = AdapterPreference::default();
+        let mut order: Vec<usize> = (0..adapters.len()).collect();
+        order.sort_by_key(|&index| {
+            std::cmp::Reverse(preference.priority(&adapters[index].info.device_type))
+        });
+
+        let mut surface = None;
+        let mut last_error = None;
+        for adapter_index in order {
+            let adapter = &adapters[adapter_index];
+
+            // Surface is consumed by failed attempt. Create new one for next adapter.
+            let new_surface = match surface.take() {
+                Some(surface) => surface,
+                None => match Surface::try_new(instance, window) {
+                    Ok(surface) => surface,
+                    Err(err) => {
+                        log::warn!(
+                            "Failed to create surface for adapter {}: {}",
+                            adapter.info.name,
+                            err
+                        );
+                        last_error = Some(err);
+                        continue;
+                    }
+                },
+            };
+
+            let family = match compatible_family(adapter, &new_surface) {
+                Some(family) => family,
+                None => {
+                    log::debug!("Adapter {} can't present to the window", adapter.info.name);
+                    surface = Some(new_surface);
+                    continue;
+                }
+            };
+
+            match Self::open_with_adapter(
+                instance,
+                new_surface,
+                adapter,
+                family,
+                window,
+                image_count,
+                usage,
+            ) {
+                Ok((target, device, queue)) => {
+                    return Ok(AdapterTarget {
+                        target,
+                        device,
+                        adapter_index,
+                        family,
+                        queue,
+                    })
+                }
+                Err(err) => {
+                    log::warn!(
+                        "Failed to create target with adapter {}: {}",
+                        adapter.info.name,
+                        err
+                    );
+                    last_error = Some(err);
+                }
+            }
+        }
+
+        if let Some(surface) = surface {
+            surface.dispose(instance);
+        }
+
+        Err(last_error
+            .unwrap_or_else(|| failure::format_err!("No adapter can present to the window")))
+    }
+
+    #[cfg(feature = "winit")]
+    fn open_with_adapter(
+        instance: &Instance<B>,
+        surface: Surface<B>,
+        adapter: &gfx_hal::Adapter<B>,
+        family: gfx_hal::queue::QueueFamilyId,
+        window: &winit::Window,
+        image_count: u32,
+        usage: gfx_hal::image::Usage,
+    ) -> Result<(Target<B>, Device<B>, B::CommandQueue), failure::Error> {
+        log::debug!("Adapter picked: {:#?}", adapter.info);
+
+        let queue_family = adapter
+            .queue_families
+            .iter()
+            .find(|queue_family| gfx_hal::queue::QueueFamily::id(*queue_family) == family)
+            .expect("Family is picked from adapter's families");
+
+        let gfx_hal::Gpu { device, mut queues } = unsafe {
+            gfx_hal::PhysicalDevice::open(
+                &adapter.physical_device,
+                &[(queue_family, &[1.0][..])],
+                gfx_hal::PhysicalDevice::features(&adapter.physical_device),
+            )
+        }?;
+
+        let queue = queues
+            .take_raw(family)
+            .and_then(|mut queues| queues.pop())
+            .expect("Queue is requested when device is opened");
+        let device = Device::new(device, instance);
+
+        let extent = WindowHandle::inner_size(window)
+            .ok_or_else(|| failure::format_err!("Window no longer exists"))?;
+
+        let (_capabilities, _formats, present_modes) =
+            unsafe { surface.compatibility(&adapter.physical_device) };
+        let present_mode = match Vsync::On.pick(&present_modes) {
+            Some(present_mode) => present_mode,
+            None => failure::bail!("Surface supports no present modes"),
+        };
+
+        let target = unsafe {
+            surface.into_target(
+                &adapter.physical_device,
+                &device,
+                extent,
+                image_count,
+                present_mode,
+                usage,
+            )
+        }?;
+
+        Ok((target, device, queue))
+    }
+
+    /// Create target from already created surface.
+    ///
+    /// Unlike [`auto`] this neither creates surface nor picks adapter,
+    /// which allows to manage surface creation separately from swapchain setup.
+    ///
+    /// # Safety
+    ///
+    /// `physical_device` must be the one `device` is opened from
+    /// and be created from same instance as the `surface`.
+    ///
+    /// [`auto`]: #method.auto
+    pub unsafe fn from_surface(
+        mut surface: Surface<B>,
+        physical_device: &B::PhysicalDevice,
+        device: &Device<B>,
+        config: TargetConfig,
+    ) -> Result<Self, failure::Error> {
+        assert_eq!(
+            device.id().instance,
+            surface.instance,
+            "Resource is not owned by specified instance"
+        );
+
+        let (swapchain_config, support) =
+            negotiate_swapchain(&surface, physical_device, &config, false)?;
+        let NewSwapchain {
+            raw: swapchain,
+            backbuffer,
+            tiling,
+        } = create_swapchain(&mut surface, device, &swapchain_config, None)?;
+
+        warn_no_srgb(&config, &support, swapchain_config.format);
+
+        Ok(Target {
+            device: device.id(),
+            relevant: relevant::Relevant,
+            surface,
+            swapchain: Some(swapchain),
+            support,
+            images_in_flight: backbuffer.iter().map(|_| None).collect(),
+            render_finished: None,
+            layouts: std::sync::Mutex::new(
+                backbuffer
+                    .iter()
+                    .map(|_| gfx_hal::image::Layout::Undefined)
+                    .collect(),
+            ),
+            pending_destroy: Vec::new(),
+            timeline_value: None,
+            generation: 0,
+            frame_interval: None,
+            last_present: std::sync::Mutex::new(None),
+            last_acquired: None,
+            last_presented: std::sync::Mutex::new(None),
+            before_present: None,
+            on_recreate: None,
+            cpu_ahead_limit: None,
+            mark_order: std::collections::VecDeque::new(),
+            present_family: None,
+            allow_non_graphics_present: false,
+            is_on_battery: None,
+            guarded: None,
+            max_recreations: 2,
+            device_lost: false,
+            image_views: None,
+            acquire_semaphores: None,
+            hdr_metadata: None,
+            acquire_wait_stage: gfx_hal::pso::PipelineStage::COLOR_ATTACHMENT_OUTPUT,
+            #[cfg(feature = "winit")]
+            monitor: None,
+            backbuffer: Some(backbuffer),
+            swapchain_config,
+            tiling,
+            config,
+        })
+    }
+
     /// Dispose of target.
     ///
     /// # Safety
@@ -382,6 +1487,16 @@ where
     pub unsafe fn dispose(mut self, device: &Device<B>) -> Surface<B> {
         self.assert_device_owner(device);
 
+        if let Some(semaphores) = self.acquire_semaphores.take() {
+            semaphores.dispose(device);
+        }
+
+        self.image_views
+            .take()
+            .into_iter()
+            .flatten()
+            .for_each(|view| device.destroy_image_view(view));
+
         match self.backbuffer {
             Some(images) => {
                 images
@@ -391,11 +1506,44 @@ where
             _ => {}
         };
 
+        self.images_in_flight
+            .drain(..)
+            .flatten()
+            .chain(self.pending_destroy.drain(..).map(|(_, _, fence)| fence))
+            .for_each(|fence| device.destroy_fence(fence));
+
+        self.render_finished
+            .take()
+            .into_iter()
+            .flatten()
+            .for_each(|semaphore| device.destroy_semaphore(semaphore));
+
         self.relevant.dispose();
         self.swapchain.take().map(|s| device.destroy_swapchain(s));
         self.surface
     }
 
+    /// Make the window fullscreen on specified monitor.
+    /// `None` leaves fullscreen mode.
+    ///
+    /// Window must be the one target is created for.
+    /// Swapchain should be [recreated] once window is resized.
+    ///
+    /// [recreated]: #method.recreate
+    #[cfg(feature = "winit")]
+    pub fn set_fullscreen(&mut self, window: &winit::Window, monitor: Option<winit::MonitorId>) {
+        window.set_fullscreen(monitor.clone());
+        self.monitor = monitor;
+    }
+
+    /// Get monitor the window is made fullscreen on with [`set_fullscreen`].
+    ///
+    /// [`set_fullscreen`]: #method.set_fullscreen
+    #[cfg(feature = "winit")]
+    pub fn monitor(&self) -> Option<&winit::MonitorId> {
+        self.monitor.as_ref()
+    }
+
     /// Get raw surface handle.
     pub fn surface(&self) -> &Surface<B> {
         &self.surface
@@ -408,149 +1556,3497 @@ where
 
     /// Recreate swapchain.
     ///
+    /// Set of present modes supported by the surface may change,
+    /// e.g. when window goes fullscreen.
+    /// If requested present mode is not supported
+    /// another one is picked according to its [`Vsync`] intent.
+    /// Requested present mode is kept in [`config`]
+    /// and used again once surface supports it.
+    ///
     /// #Safety
     ///
     /// Current swapchain must be not in use.
+    ///
+    /// [`Vsync`]: enum.Vsync.html
+    /// [`config`]: #method.config
     pub unsafe fn recreate(
         &mut self,
         physical_device: &B::PhysicalDevice,
         device: &Device<B>,
         suggest_extent: Extent2D,
-    ) -> Result<(), failure::Error> {
+    ) -> Result<RecreateOutcome, failure::Error> {
         self.assert_device_owner(device);
 
-        let image_count = match self.backbuffer.take() {
-            Some(images) => {
-                let count = images.len();
-                images
-                    .into_iter()
-                    .for_each(|image| image.dispose_swapchain_image(device.id()));
-                count
-            }
-            None => 0,
-        };
-
-        self.swapchain.take().map(|s| device.destroy_swapchain(s));
+        let config = self.config.with_extent(suggest_extent);
+        let (swapchain_config, support) =
+            self.negotiate_requested(&self.surface, physical_device, &config)?;
 
-        let (swapchain, backbuffer, extent) = create_swapchain(
-            &mut self.surface,
-            physical_device,
-            device,
-            suggest_extent,
-            image_count as u32,
-            self.present_mode,
-            self.usage,
-        )?;
+        self.replace_swapchain(device, config, swapchain_config, support, true)
+    }
 
-        self.swapchain.replace(swapchain);
-        self.backbuffer.replace(backbuffer);
-        self.extent = extent;
+    /// Negotiate swapchain for requested `config` with `surface`.
+    /// Unlike [`recreate_with_config`] this falls back to another present mode
+    /// if requested one is not supported.
+    ///
+    /// [`recreate_with_config`]: #method.recreate_with_config
+    unsafe fn negotiate_requested(
+        &self,
+        surface: &Surface<B>,
+        physical_device: &B::PhysicalDevice,
+        config: &TargetConfig,
+    ) -> Result<(gfx_hal::SwapchainConfig, SwapchainSupport), failure::Error> {
+        let (_capabilities, _formats, present_modes) = surface.compatibility(physical_device);
+        let effective = fallback_present_mode(*config, &present_modes)?;
 
-        Ok(())
+        let on_battery = self
+            .is_on_battery
+            .as_ref()
+            .map_or(false, |is_on_battery| is_on_battery());
+        negotiate_swapchain(surface, physical_device, &effective, on_battery)
     }
 
-    /// Get swapchain impl trait.
+    /// Replace lost surface with the new one and create swapchain for it.
+    /// Returns the old surface.
+    ///
+    /// Should be called after acquisition or presentation fails with `SurfaceLost`,
+    /// e.g. when display is unplugged.
+    /// [`recreate`] can't help in that case as the swapchain is bound to the surface.
+    ///
+    /// `family` is the queue family used for presentation.
+    /// The new surface is checked to support it and current config
+    /// before the old swapchain is destroyed.
+    ///
+    /// On failure the new surface is returned along with the error
+    /// and the target keeps the old surface.
+    /// If the check fails the old swapchain is left intact.
+    /// Otherwise the old swapchain is already destroyed
+    /// and target is left without swapchain images until recreated.
     ///
     /// # Safety
     ///
-    /// Trait usage should not violate this type valid usage.
-    pub unsafe fn swapchain_mut(&mut self) -> &mut impl gfx_hal::Swapchain<B> {
-        self.swapchain.as_mut().expect("Swapchain already disposed")
-    }
+    /// Current swapchain must be not in use.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `surface` is created from another instance.
+    ///
+    /// [`recreate`]: #method.recreate
+    pub unsafe fn recreate_surface(
+        &mut self,
+        surface: Surface<B>,
+        physical_device: &B::PhysicalDevice,
+        device: &Device<B>,
+        family: &B::QueueFamily,
+        suggest_extent: Extent2D,
+    ) -> Result<Surface<B>, (Surface<B>, failure::Error)> {
+        self.assert_device_owner(device);
+        assert_eq!(
+            self.surface.instance, surface.instance,
+            "New surface must be created from the same instance"
+        );
 
-    /// Get raw handlers for the swapchain images.
-    pub fn backbuffer(&self) -> &Vec<Image<B>> {
-        self.backbuffer
-            .as_ref()
-            .expect("Swapchain already disposed")
-    }
+        if !gfx_hal::Surface::supports_queue_family(surface.raw(), family) {
+            return Err((
+                surface,
+                failure::format_err!("Queue family can't present to the new surface"),
+            ));
+        }
 
-    /// Get render target size.
-    pub fn extent(&self) -> Extent2D {
-        self.extent
-    }
+        let config = self.config.with_extent(suggest_extent);
+        let (swapchain_config, support) =
+            match self.negotiate_requested(&surface, physical_device, &config) {
+                Ok(negotiated) => negotiated,
+                Err(err) => return Err((surface, err)),
+            };
 
-    /// Get image usage flags.
-    pub fn usage(&self) -> gfx_hal::image::Usage {
-        self.usage
+        let old = std::mem::replace(&mut self.surface, surface);
+        match self.replace_swapchain(device, config, swapchain_config, support, false) {
+            Ok(_) => Ok(old),
+            Err(err) => Err((std::mem::replace(&mut self.surface, old), err)),
+        }
     }
 
-    /// Acquire next image.
-    pub unsafe fn next_image(
+    /// Recreate swapchain only if surface extent or capabilities changed.
+    /// Returns `None` if swapchain is kept.
+    ///
+    /// Intended for window events like resize or fullscreen toggle
+    /// that don't necessarily affect the surface,
+    /// e.g. toggling borderless fullscreen on a window already covering the display.
+    /// Skipping recreation avoids visible flash.
+    ///
+    /// Swapchain reported out of date must be recreated with [`recreate`] instead,
+    /// as capabilities may stay the same in that case.
+    ///
+    /// #Safety
+    ///
+    /// Current swapchain must be not in use.
+    ///
+    /// [`recreate`]: #method.recreate
+    pub unsafe fn recreate_if_changed(
         &mut self,
-        signal: &B::Semaphore,
-    ) -> Result<NextImages<'_, B>, gfx_hal::AcquireError> {
-        let index = gfx_hal::Swapchain::acquire_image(
-            // Missing swapchain is equivalent to OutOfDate, as it has to be recreated anyway.
-            self.swapchain
-                .as_mut()
-                .ok_or(gfx_hal::AcquireError::OutOfDate)?,
-            !0,
-            Some(signal),
-            None,
-        )?
-        .0;
-
-        Ok(NextImages {
-            targets: std::iter::once((&*self, index)).collect(),
-        })
-    }
-}
-
-/// Represents acquire frames that will be presented next.
-#[derive(Debug)]
-pub struct NextImages<'a, B: Backend> {
-    targets: smallvec::SmallVec<[(&'a Target<B>, u32); 8]>,
-}
+        physical_device: &B::PhysicalDevice,
+        device: &Device<B>,
+        suggest_extent: Extent2D,
+    ) -> Result<Option<RecreateOutcome>, failure::Error> {
+        let (capabilities, _formats, _present_modes) = self.surface.compatibility(physical_device);
+        if self.swapchain.is_some()
+            && swapchain_extent(&capabilities, suggest_extent) == self.swapchain_config.extent
+            && !self.capabilities_changed(physical_device)
+        {
+            log::trace!("Surface is not changed. Swapchain is kept");
+            return Ok(None);
+        }
 
-impl<'a, B> NextImages<'a, B>
-where
-    B: Backend,
-{
-    /// Get indices.
-    pub fn indices(&self) -> impl IntoIterator<Item = u32> + '_ {
-        self.targets.iter().map(|(_s, i)| *i)
+        self.recreate(physical_device, device, suggest_extent)
+            .map(Some)
     }
 
-    /// Present images by the queue.
+    /// Recreate swapchain applying all parameters from `config` at once.
     ///
-    /// # TODO
+    /// The config is validated against surface capabilities
+    /// before current swapchain is retired.
+    /// If validation fails current swapchain is left intact.
     ///
-    /// Use specific presentation error type.
-    pub unsafe fn present<'b>(
+    /// Current swapchain is passed to the backend as old swapchain.
+    /// gfx-hal takes ownership of it, so it can't be retained by the target.
+    /// Fences [marked] for its images are retained instead,
+    /// until [`collect_garbage`] finds them signaled.
+    ///
+    /// [marked]: #method.mark_in_flight
+    /// [`collect_garbage`]: #method.collect_garbage
+    ///
+    /// #Safety
+    ///
+    /// Current swapchain must be not in use.
+    pub unsafe fn recreate_with_config(
+        &mut self,
+        config: TargetConfig,
+        physical_device: &B::PhysicalDevice,
+        device: &Device<B>,
+    ) -> Result<RecreateOutcome, failure::Error> {
+        self.assert_device_owner(device);
+
+        let on_battery = self
+            .is_on_battery
+            .as_ref()
+            .map_or(false, |is_on_battery| is_on_battery());
+        let (swapchain_config, support) =
+            negotiate_swapchain(&self.surface, physical_device, &config, on_battery)?;
+
+        self.replace_swapchain(device, config, swapchain_config, support, true)
+    }
+
+    /// Destroy image views, release images and retain marked fences of current swapchain.
+    /// Returns current swapchain.
+    ///
+    /// Target is left without swapchain images, but consistent,
+    /// so that it can be recreated again if new swapchain can't be created.
+    unsafe fn retire_swapchain(&mut self, device: &Device<B>) -> Option<B::Swapchain> {
+        self.image_views
+            .take()
+            .into_iter()
+            .flatten()
+            .for_each(|view| device.destroy_image_view(view));
+
+        if let Some(images) = self.backbuffer.replace(Vec::new()) {
+            images
+                .into_iter()
+                .for_each(|image| image.dispose_swapchain_image(device.id()));
+        }
+
+        let generation = self.generation;
+        let timeline_value = self.timeline_value;
+        self.pending_destroy.extend(
+            self.images_in_flight
+                .drain(..)
+                .flatten()
+                .map(|fence| (generation, timeline_value, fence)),
+        );
+
+        self.mark_order.clear();
+        self.last_acquired = None;
+        *self
+            .last_presented
+            .get_mut()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+        if let Some(guarded) = &mut self.guarded {
+            guarded.clear();
+        }
+        self.layouts
+            .get_mut()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clear();
+
+        self.swapchain.take()
+    }
+
+    /// Replace current swapchain with one created with negotiated parameters.
+    /// Current swapchain is passed to the backend as old one if `reuse_old` is set
+    /// and destroyed otherwise.
+    unsafe fn replace_swapchain(
+        &mut self,
+        device: &Device<B>,
+        config: TargetConfig,
+        swapchain_config: gfx_hal::SwapchainConfig,
+        support: SwapchainSupport,
+        reuse_old: bool,
+    ) -> Result<RecreateOutcome, failure::Error> {
+        let had_image_views = self.image_views.is_some();
+        let old_swapchain = self.retire_swapchain(device);
+        let old_swapchain = match old_swapchain {
+            Some(swapchain) if !reuse_old => {
+                device.destroy_swapchain(swapchain);
+                None
+            }
+            old_swapchain => old_swapchain,
+        };
+
+        let created = create_swapchain(&mut self.surface, device, &swapchain_config, old_swapchain);
+        let NewSwapchain {
+            raw: swapchain,
+            backbuffer,
+            tiling,
+        } = match created {
+            Ok(created) => created,
+            Err(err) => {
+                if had_image_views {
+                    // Keep views requested so that next recreation creates them.
+                    self.image_views = Some(Vec::new());
+                }
+                return Err(err);
+            }
+        };
+
+        // Create everything that may fail before touching the target,
+        // so that on error new swapchain can be destroyed and target is left
+        // in the consistent state `retire_swapchain` put it in.
+        let created = self.create_swapchain_resources(
+            device,
+            &config,
+            &swapchain_config,
+            &backbuffer,
+            had_image_views,
+        );
+        let (render_finished, image_views) = match created {
+            Ok(created) => created,
+            Err(err) => {
+                if had_image_views {
+                    // Keep views requested so that next recreation creates them.
+                    self.image_views = Some(Vec::new());
+                }
+                backbuffer
+                    .into_iter()
+                    .for_each(|image| image.dispose_swapchain_image(device.id()));
+                device.destroy_swapchain(swapchain);
+                return Err(err);
+            }
+        };
+
+        if swapchain_config.format != self.swapchain_config.format {
+            warn_no_srgb(&config, &support, swapchain_config.format);
+        }
+
+        self.generation += 1;
+        let outcome = RecreateOutcome {
+            old_present_mode: self.swapchain_config.present_mode,
+            present_mode: swapchain_config.present_mode,
+            old_extent: self.swapchain_config.extent,
+            extent: swapchain_config.extent,
+            old_format: self.swapchain_config.format,
+            format: swapchain_config.format,
+            generation: self.generation,
+        };
+
+        self.images_in_flight = backbuffer.iter().map(|_| None).collect();
+        if let Some(guarded) = &mut self.guarded {
+            *guarded = backbuffer.iter().map(|_| false).collect();
+        }
+        *self
+            .layouts
+            .get_mut()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = backbuffer
+            .iter()
+            .map(|_| gfx_hal::image::Layout::Undefined)
+            .collect();
+        if let Some(render_finished) = render_finished {
+            self.render_finished
+                .replace(render_finished)
+                .into_iter()
+                .flatten()
+                .for_each(|semaphore| device.destroy_semaphore(semaphore));
+        }
+        self.image_views = image_views;
+
+        self.swapchain.replace(swapchain);
+        self.backbuffer.replace(backbuffer);
+        self.swapchain_config = swapchain_config;
+        self.tiling = tiling;
+        self.support = support;
+        self.config = config;
+
+        if let Some(callback) = &mut self.on_recreate {
+            let callback = callback
+                .get_mut()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            (&mut *callback)(&outcome);
+        }
+
+        Ok(outcome)
+    }
+
+    /// Create render finished semaphores for new swapchain images if target has them,
+    /// image views if `create_views` is set, and reset acquire semaphore pool.
+    /// Anything created is destroyed on error.
+    unsafe fn create_swapchain_resources(
+        &mut self,
+        device: &Device<B>,
+        config: &TargetConfig,
+        swapchain_config: &gfx_hal::SwapchainConfig,
+        backbuffer: &[Image<B>],
+        create_views: bool,
+    ) -> Result<(Option<Vec<B::Semaphore>>, Option<Vec<B::ImageView>>), failure::Error> {
+        let render_finished = match self.render_finished {
+            Some(_) => {
+                let mut semaphores = Vec::with_capacity(backbuffer.len());
+                for _ in backbuffer {
+                    match device.create_semaphore() {
+                        Ok(semaphore) => semaphores.push(semaphore),
+                        Err(err) => {
+                            semaphores
+                                .into_iter()
+                                .for_each(|semaphore| device.destroy_semaphore(semaphore));
+                            return Err(err.into());
+                        }
+                    }
+                }
+                Some(semaphores)
+            }
+            None => None,
+        };
+
+        let destroy_render_finished = |render_finished: Option<Vec<B::Semaphore>>| {
+            render_finished
+                .into_iter()
+                .flatten()
+                .for_each(|semaphore| device.destroy_semaphore(semaphore));
+        };
+
+        let image_views = if create_views {
+            match create_image_views(device, backbuffer, swapchain_config) {
+                Ok(views) => Some(views),
+                Err(err) => {
+                    destroy_render_finished(render_finished);
+                    return Err(err.into());
+                }
+            }
+        } else {
+            None
+        };
+
+        if let Some(semaphores) = &mut self.acquire_semaphores {
+            let image_count = backbuffer.len() as u32;
+            // Pool left partially refilled on error is still consistent, next reset tops it up.
+            if let Err(err) = semaphores.reset(
+                device,
+                image_count,
+                acquire_semaphore_pool_size(config, image_count),
+            ) {
+                destroy_render_finished(render_finished);
+                image_views
+                    .into_iter()
+                    .flatten()
+                    .for_each(|view| device.destroy_image_view(view));
+                return Err(err.into());
+            }
+        }
+
+        Ok((render_finished, image_views))
+    }
+
+    /// Wait for all fences [marked] for swapchain images.
+    ///
+    /// Call before [`recreate`] to make sure tracked submissions using
+    /// current swapchain images are complete and old swapchain can be retired,
+    /// without waiting for unrelated work as `wait_idle` does.
+    /// Unlike [`drain`] this doesn't wait for fences retained from previous swapchains.
+    ///
+    /// [marked]: #method.mark_in_flight
+    /// [`recreate`]: #method.recreate
+    /// [`drain`]: #method.drain
+    pub fn wait_for_all_images(
+        &self,
+        device: &Device<B>,
+    ) -> Result<(), gfx_hal::device::OomOrDeviceLost> {
+        self.assert_device_owner(device);
+
+        let fences: smallvec::SmallVec<[&B::Fence; 16]> =
+            self.images_in_flight.iter().flatten().collect();
+
+        if !fences.is_empty() {
+            unsafe {
+                device.wait_for_fences(fences, gfx_hal::device::WaitFor::All, !0)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Wait for all fences [marked] for swapchain images
+    /// and fences retained from previous swapchains.
+    /// Retained fences are destroyed afterwards.
+    ///
+    /// Only submissions tracked with marked fences are waited for.
+    /// If all submissions using swapchain images are tracked,
+    /// swapchain is no longer in use after this call and target can be disposed.
+    ///
+    /// [marked]: #method.mark_in_flight
+    pub fn drain(&mut self, device: &Device<B>) -> Result<(), gfx_hal::device::OomOrDeviceLost> {
+        self.assert_device_owner(device);
+
+        let fences: smallvec::SmallVec<[&B::Fence; 16]> = self
+            .images_in_flight
+            .iter()
+            .flatten()
+            .chain(self.pending_destroy.iter().map(|(_, _, fence)| fence))
+            .collect();
+
+        if !fences.is_empty() {
+            unsafe {
+                device.wait_for_fences(fences, gfx_hal::device::WaitFor::All, !0)?;
+            }
+        }
+
+        unsafe {
+            self.collect_garbage(device);
+        }
+
+        Ok(())
+    }
+
+    /// Destroy resources retained from previous swapchains
+    /// that are no longer used by the device.
+    ///
+    /// Should be called periodically, e.g. once per frame.
+    pub unsafe fn collect_garbage(&mut self, device: &Device<B>) {
+        self.collect_garbage_with(device, |_| false)
+    }
+
+    /// Set timeline value that is reached once all work submitted so far is complete.
+    ///
+    /// Fences [marked] for images of swapchain retired by recreation
+    /// are tagged with the last value set.
+    /// [`collect_garbage_until`] then destroys them once the timeline passes that value.
+    /// Image views of retired swapchain are destroyed right away, only fences are retained.
+    ///
+    /// The timeline is tracked by the application, e.g. as a frame counter.
+    ///
+    /// [marked]: #method.mark_in_flight
+    /// [`collect_garbage_until`]: #method.collect_garbage_until
+    pub fn set_timeline_value(&mut self, value: u64) {
+        self.timeline_value = Some(value);
+    }
+
+    /// Destroy fences retained from previous swapchains
+    /// retired at timeline value not greater than `completed`
+    /// without checking their status.
+    ///
+    /// Fences retired at greater timeline value or before any [timeline value] was set
+    /// are still destroyed if they are signaled, as with [`collect_garbage`].
+    ///
+    /// [timeline value]: #method.set_timeline_value
+    /// [`collect_garbage`]: #method.collect_garbage
+    pub unsafe fn collect_garbage_until(&mut self, device: &Device<B>, completed: u64) {
+        self.collect_garbage_with(device, |value| value <= completed)
+    }
+
+    unsafe fn collect_garbage_with(&mut self, device: &Device<B>, passed: impl Fn(u64) -> bool) {
+        self.assert_device_owner(device);
+
+        let (complete, pending): (Vec<_>, Vec<_>) =
+            self.pending_destroy
+                .drain(..)
+                .partition(|(_, timeline_value, fence)| match *timeline_value {
+                    Some(value) if passed(value) => true,
+                    // Nothing is in use after device is lost.
+                    _ => device.get_fence_status(fence).unwrap_or(true),
+                });
+
+        for (generation, _, fence) in complete {
+            log::trace!(
+                "Destroy fence retired from swapchain generation {}",
+                generation
+            );
+            device.destroy_fence(fence);
+        }
+
+        self.pending_destroy = pending;
+    }
+
+    /// Limit frame rate when present mode doesn't wait for vertical blank.
+    ///
+    /// With `Immediate` and `Mailbox` present modes frames are rendered
+    /// as fast as possible. With frame cap set, presentation sleeps
+    /// until at least `1 / fps` seconds passed since previous presentation.
+    /// Frame cap has no effect with other present modes.
+    ///
+    /// `None` or zero removes the cap. There is no cap by default.
+    pub fn set_frame_cap(&mut self, fps: Option<u32>) {
+        self.frame_interval = fps
+            .filter(|&fps| fps > 0)
+            .map(|fps| std::time::Duration::from_secs(1) / fps);
+    }
+
+    /// Set callback invoked right before image is presented
+    /// with index of the image.
+    /// Useful for inserting profiling markers or measuring latency.
+    ///
+    /// `None` removes the callback. There is no callback by default.
+    pub fn on_before_present(&mut self, callback: Option<Box<dyn FnMut(ImageIndex) + Send>>) {
+        self.before_present = callback.map(std::sync::Mutex::new);
+    }
+
+    /// Set callback invoked after swapchain is successfully recreated
+    /// with changes made by recreation.
+    ///
+    /// `None` removes the callback. There is no callback by default.
+    pub fn on_recreate(&mut self, callback: Option<Box<dyn FnMut(&RecreateOutcome) + Send>>) {
+        self.on_recreate = callback.map(std::sync::Mutex::new);
+    }
+
+    /// Prepare image at `index` for presentation.
+    /// Respects frame cap and invokes before-present callback.
+    fn before_present(&self, index: ImageIndex) {
+        #[cfg(debug_assertions)]
+        {
+            if let Some(guarded) = &self.guarded {
+                if !guarded[index.0 as usize] {
+                    log::error!(
+                        "Image {} is presented but no fence was marked for it since it was acquired",
+                        index.0
+                    );
+                }
+            }
+        }
+
+        *self
+            .last_presented
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(index);
+
+        self.throttle();
+
+        if let Some(callback) = &self.before_present {
+            let mut callback = callback
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            (&mut *callback)(index);
+        }
+    }
+
+    /// Sleep before presentation to respect frame cap.
+    fn throttle(&self) {
+        let interval = match self.frame_interval {
+            Some(interval) => interval,
+            None => return,
+        };
+
+        let mut last_present = self
+            .last_present
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        match self.swapchain_config.present_mode {
+            gfx_hal::PresentMode::Immediate | gfx_hal::PresentMode::Mailbox => {
+                if let Some(last) = *last_present {
+                    let elapsed = last.elapsed();
+                    if elapsed < interval {
+                        std::thread::sleep(interval - elapsed);
+                    }
+                }
+            }
+            gfx_hal::PresentMode::Fifo | gfx_hal::PresentMode::Relaxed => {}
+        }
+
+        *last_present = Some(std::time::Instant::now());
+    }
+
+    /// Get number of times swapchain was recreated.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Recreate swapchain with different image usage.
+    ///
+    /// Fails if surface doesn't support requested usage.
+    /// Current swapchain is left intact in that case.
+    ///
+    /// #Safety
+    ///
+    /// Current swapchain must be not in use.
+    pub unsafe fn set_usage(
+        &mut self,
+        usage: gfx_hal::image::Usage,
+        physical_device: &B::PhysicalDevice,
+        device: &Device<B>,
+    ) -> Result<RecreateOutcome, failure::Error> {
+        self.assert_device_owner(device);
+
+        let config = self.config.with_usage(usage);
+        let (swapchain_config, support) =
+            self.negotiate_requested(&self.surface, physical_device, &config)?;
+
+        self.replace_swapchain(device, config, swapchain_config, support, true)
+    }
+
+    /// Get swapchain impl trait.
+    ///
+    /// # Safety
+    ///
+    /// Trait usage should not violate this type valid usage.
+    pub unsafe fn swapchain_mut(&mut self) -> &mut impl gfx_hal::Swapchain<B> {
+        self.swapchain.as_mut().expect("Swapchain already disposed")
+    }
+
+    /// Get raw surface and swapchain mutably at once.
+    ///
+    /// # Safety
+    ///
+    /// Usage of raw handles should not violate this type valid usage.
+    pub unsafe fn raw_parts_mut(&mut self) -> (&mut B::Surface, &mut B::Swapchain) {
+        (
+            &mut self.surface.raw,
+            self.swapchain.as_mut().expect("Swapchain already disposed"),
+        )
+    }
+
+    /// Reset image tracking after the swapchain was used through [`swapchain_mut`]
+    /// or [`raw_parts_mut`].
+    ///
+    /// Layouts of all images are reset to `Undefined`
+    /// and no image is considered acquired or presented.
+    /// Fences marked for the images are kept.
+    /// Generation is not changed as swapchain is not recreated.
+    ///
+    /// [`swapchain_mut`]: #method.swapchain_mut
+    /// [`raw_parts_mut`]: #method.raw_parts_mut
+    pub fn invalidate_tracking(&mut self) {
+        let image_count = self.image_count() as usize;
+        self.last_acquired = None;
+        *self
+            .last_presented
+            .get_mut()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+        if let Some(guarded) = &mut self.guarded {
+            *guarded = vec![false; image_count];
+        }
+        *self
+            .layouts
+            .get_mut()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) =
+            vec![gfx_hal::image::Layout::Undefined; image_count];
+    }
+
+    /// Get raw handlers for the swapchain images.
+    pub fn backbuffer(&self) -> &Vec<Image<B>> {
+        self.backbuffer
+            .as_ref()
+            .expect("Swapchain already disposed")
+    }
+
+    /// Get render target size.
+    ///
+    /// This is the extent of the swapchain images
+    /// and should be used to setup viewports and scissors.
+    /// It may differ from [`surface_extent`] until swapchain is recreated.
+    ///
+    /// [`surface_extent`]: #method.surface_extent
+    pub fn extent(&self) -> Extent2D {
+        self.swapchain_config.extent
+    }
+
+    /// Get number of images in the swapchain.
+    ///
+    /// This is the number of images actually created by presentation engine
+    /// and may differ from requested one.
+    /// Updated when swapchain is recreated.
+    /// Per-image resources should be allocated for this many images.
+    pub fn image_count(&self) -> u32 {
+        self.backbuffer().len() as u32
+    }
+
+    /// Estimate memory consumed by swapchain images in bytes.
+    ///
+    /// Computed from number of images, extent, layers and format size.
+    /// Drivers may pad and align images and allocate auxiliary data,
+    /// so actual consumption is likely higher.
+    pub fn estimated_memory_bytes(&self) -> u64 {
+        let desc = self.format().surface_desc();
+        let extent = self.extent();
+        let block_width = desc.dim.0 as u64;
+        let block_height = desc.dim.1 as u64;
+        let blocks = ((extent.width as u64 + block_width - 1) / block_width)
+            * ((extent.height as u64 + block_height - 1) / block_height);
+
+        self.image_count() as u64
+            * blocks
+            * (desc.bits as u64 / 8)
+            * self.swapchain_config.image_layers as u64
+    }
+
+    /// Check if surface is still usable for presentation,
+    /// e.g. after display is disconnected.
+    ///
+    /// Surface queries can't fail,
+    /// so surface is considered invalid only if it reports no present modes
+    /// or no swapchain images at all.
+    /// Otherwise this returns `true` and invalid surface is reported
+    /// by acquisition or presentation failing.
+    pub unsafe fn is_surface_valid(&self, physical_device: &B::PhysicalDevice) -> bool {
+        let (capabilities, _formats, present_modes) = self.surface.compatibility(physical_device);
+        !present_modes.is_empty() && capabilities.image_count.end > 0
+    }
+
+    /// Check if surface capabilities changed since swapchain was created.
+    ///
+    /// Supported formats, present modes, image usage and composite alpha modes are compared.
+    /// They may change when window is moved to another display.
+    /// Swapchain should be recreated with [`recreate_with_config`] in that case.
+    ///
+    /// [`recreate_with_config`]: #method.recreate_with_config
+    pub unsafe fn capabilities_changed(&self, physical_device: &B::PhysicalDevice) -> bool {
+        let (capabilities, formats, present_modes) = self.surface.compatibility(physical_device);
+        let support = SwapchainSupport {
+            formats,
+            present_modes,
+            usage: capabilities.usage,
+            composite_alpha: capabilities.composite_alpha,
+        };
+
+        support != self.support
+    }
+
+    /// Get current extent of the surface.
+    ///
+    /// This is the extent of the window area the swapchain images are presented to.
+    /// Returns `None` if surface extent is determined by the swapchain extent,
+    /// which is the case on some platforms, e.g. Wayland.
+    pub unsafe fn surface_extent(&self, physical_device: &B::PhysicalDevice) -> Option<Extent2D> {
+        self.surface.extent(physical_device)
+    }
+
+    /// Check if current extent of the surface differs from swapchain extent
+    /// by more than `threshold` pixels in either dimension.
+    ///
+    /// Intended to be called periodically to catch missed resize events.
+    /// Swapchain is not recreated automatically.
+    /// Returns `false` if surface doesn't report its current extent.
+    pub unsafe fn check_extent_drift(
+        &self,
+        physical_device: &B::PhysicalDevice,
+        threshold: u32,
+    ) -> bool {
+        let extent = self.extent();
+        match self.surface_extent(physical_device) {
+            Some(current) => {
+                let drift = (current.width as i64 - extent.width as i64)
+                    .abs()
+                    .max((current.height as i64 - extent.height as i64).abs());
+                if drift > threshold as i64 {
+                    log::warn!(
+                        "Surface extent {:?} drifted from swapchain extent {:?}",
+                        current,
+                        extent
+                    );
+                    true
+                } else {
+                    false
+                }
+            }
+            None => false,
+        }
+    }
+
+    /// Get aspect ratio of the render target.
+    /// Returns `1.0` if height is zero, which happens when window is minimized.
+    pub fn aspect_ratio(&self) -> f32 {
+        let extent = self.extent();
+        if extent.height == 0 {
+            1.0
+        } else {
+            extent.width as f32 / extent.height as f32
+        }
+    }
+
+    /// Get number of pixels in the render target image.
+    pub fn pixel_count(&self) -> u32 {
+        let extent = self.extent();
+        extent.width * extent.height
+    }
+
+    /// Get scissor rectangle covering whole swapchain image.
+    /// Reflects new extent after swapchain is recreated.
+    ///
+    /// Rectangle size is limited to `i16::max_value()`,
+    /// larger images are covered only partially.
+    pub fn full_scissor(&self) -> gfx_hal::pso::Rect {
+        let extent = self.extent();
+        let clamp = |size: u32| size.min(i16::max_value() as u32) as i16;
+        gfx_hal::pso::Rect {
+            x: 0,
+            y: 0,
+            w: clamp(extent.width),
+            h: clamp(extent.height),
+        }
+    }
+
+    /// Get viewport covering whole swapchain image with `0.0 .. 1.0` depth range.
+    /// See [`full_viewport_with_depth`] to specify depth range.
+    ///
+    /// [`full_viewport_with_depth`]: #method.full_viewport_with_depth
+    pub fn full_viewport(&self) -> gfx_hal::pso::Viewport {
+        self.full_viewport_with_depth(0.0..1.0)
+    }
+
+    /// Get viewport covering whole swapchain image with specified depth range.
+    pub fn full_viewport_with_depth(&self, depth: std::ops::Range<f32>) -> gfx_hal::pso::Viewport {
+        gfx_hal::pso::Viewport {
+            rect: self.full_scissor(),
+            depth,
+        }
+    }
+
+    /// Get channel order of the swapchain format.
+    /// Returns `None` for formats that are neither RGBA nor BGRA.
+    pub fn channel_order(&self) -> Option<ChannelOrder> {
+        ChannelOrder::from_format(self.format())
+    }
+
+    /// Get color space swapchain images are presented in.
+    /// Always `ColorSpace::Srgb`, see [backend limitations](index.html#backend-limitations).
+    pub fn color_space(&self) -> ColorSpace {
+        ColorSpace::Srgb
+    }
+
+    /// Check if wide gamut or high dynamic range color space is negotiated.
+    /// Always `false`, see [backend limitations](index.html#backend-limitations).
+    pub fn is_hdr(&self) -> bool {
+        self.color_space().is_hdr()
+    }
+
+    /// Get present mode of the swapchain.
+    ///
+    /// May differ from present mode in the [`config`] when it had to fall back.
+    ///
+    /// [`config`]: #method.config
+    pub fn present_mode(&self) -> gfx_hal::PresentMode {
+        self.swapchain_config.present_mode
+    }
+
+    /// Check if presentation with current present mode can cause visible tearing.
+    ///
+    /// `Immediate` always may tear and `Relaxed` tears when frame misses vertical blank.
+    /// `Fifo` and `Mailbox` never tear.
+    pub fn can_tear(&self) -> bool {
+        match self.present_mode() {
+            gfx_hal::PresentMode::Immediate | gfx_hal::PresentMode::Relaxed => true,
+            gfx_hal::PresentMode::Fifo | gfx_hal::PresentMode::Mailbox => false,
+        }
+    }
+
+    /// Set HDR metadata of presented content.
+    /// Only stored by the target, see [backend limitations](index.html#backend-limitations).
+    pub fn set_hdr_metadata(&mut self, metadata: HdrMetadata) {
+        log::debug!(
+            "HDR metadata is not supported by gfx-hal 0.2 swapchain. {:?} is ignored",
+            metadata
+        );
+        self.hdr_metadata = Some(metadata);
+    }
+
+    /// Get HDR metadata set with [`set_hdr_metadata`].
+    ///
+    /// [`set_hdr_metadata`]: #method.set_hdr_metadata
+    pub fn hdr_metadata(&self) -> Option<HdrMetadata> {
+        self.hdr_metadata
+    }
+
+    /// Get format of the swapchain images.
+    pub fn format(&self) -> gfx_hal::format::Format {
+        self.swapchain_config.format
+    }
+
+    /// Get sample counts supported by color attachments with swapchain images format.
+    /// This is intersection of framebuffer color sample counts limit
+    /// and sample counts supported for the format.
+    ///
+    /// Each supported count is set as a bit with the same value.
+    /// I.e. `counts & 4 != 0` means 4 samples are supported.
+    pub fn supported_sample_counts(
+        &self,
+        physical_device: &B::PhysicalDevice,
+    ) -> gfx_hal::image::NumSamples {
+        let limits = gfx_hal::PhysicalDevice::limits(physical_device);
+        gfx_hal::PhysicalDevice::image_format_properties(
+            physical_device,
+            self.format(),
+            2,
+            self.tiling,
+            gfx_hal::image::Usage::COLOR_ATTACHMENT,
+            gfx_hal::image::ViewCapabilities::empty(),
+        )
+        .map_or(0, |properties| {
+            properties.sample_count_mask & limits.framebuffer_color_samples_count
+        })
+    }
+
+    /// Get refresh rate of the display the surface is presented to.
+    /// Always `None`, see [backend limitations](index.html#backend-limitations).
+    pub fn refresh_rate_hz(&self) -> Option<f32> {
+        None
+    }
+
+    /// Check if swapchain images can be allocated by the application.
+    /// Always `false`, see [backend limitations](index.html#backend-limitations).
+    pub fn images_app_allocatable(&self) -> bool {
+        false
+    }
+
+    /// Get usage flags the swapchain images were created with.
+    pub fn usage(&self) -> gfx_hal::image::Usage {
+        self.swapchain_config.image_usage
+    }
+
+    /// Check if swapchain images can be read back, e.g. for screenshots.
+    /// Requires `TRANSFER_SRC` usage, see [`TargetConfig::with_transfer_src`].
+    ///
+    /// [`TargetConfig::with_transfer_src`]: struct.TargetConfig.html#method.with_transfer_src
+    pub fn supports_capture(&self) -> bool {
+        self.usage().contains(gfx_hal::image::Usage::TRANSFER_SRC)
+    }
+
+    /// Get info of the swapchain images.
+    /// Reflects parameters negotiated with the surface rather than requested ones.
+    pub fn image_info(&self) -> ImageInfo {
+        swapchain_image_info(&self.swapchain_config, self.tiling)
+    }
+
+    /// Get tiling of the swapchain images.
+    ///
+    /// Always `Optimal` which is what presentation engines use.
+    pub fn tiling(&self) -> gfx_hal::image::Tiling {
+        self.tiling
+    }
+
+    /// Get config requested for the swapchain.
+    ///
+    /// Swapchain may be created with another present mode if requested one
+    /// is not supported by the surface or replaced to save power.
+    /// Use [`describe`] to get parameters swapchain is actually created with.
+    ///
+    /// [`describe`]: #method.describe
+    pub fn config(&self) -> TargetConfig {
+        self.config
+    }
+
+    /// Get all parameters negotiated with the surface when swapchain was created.
+    ///
+    /// Implements `Display` to be included into bug reports and diagnostics.
+    pub fn describe(&self) -> TargetDescription {
+        TargetDescription {
+            backend: backend_kind::<B>().map_or("unknown", |kind| kind.name()),
+            format: self.swapchain_config.format,
+            present_mode: self.swapchain_config.present_mode,
+            composite_alpha: self.swapchain_config.composite_alpha,
+            image_count: self.image_count(),
+            usage: self.swapchain_config.image_usage,
+            extent: self.swapchain_config.extent,
+        }
+    }
+
+    /// Record commands that clear swapchain image at `index` with specified color
+    /// and transition it from layout returned by [`image_layout`] into `Present` layout.
+    ///
+    /// Useful to avoid presenting garbage before first frame is rendered.
+    /// Call for acquired images that are still in `Undefined` layout
+    /// according to [`image_layout`], which is the case on first acquisition
+    /// after swapchain is created. Swapchain images can't be accessed before acquisition,
+    /// so they can't be cleared all at once.
+    /// Swapchain must be created with `TRANSFER_DST` usage.
+    ///
+    /// # Safety
+    ///
+    /// Swapchain image at `index` must be acquired.
+    /// `buffer` must be allocated from pool of queue family
+    /// that supports graphics or compute operations.
+    ///
+    /// [`image_layout`]: #method.image_layout
+    pub unsafe fn clear_image(
+        &self,
+        buffer: &mut impl gfx_hal::command::RawCommandBuffer<B>,
+        index: ImageIndex,
+        color: gfx_hal::command::ClearColor,
+    ) -> Result<(), failure::Error> {
+        let image = &self.backbuffer()[index.0 as usize];
+
+        if !self.usage().contains(gfx_hal::image::Usage::TRANSFER_DST) {
+            failure::bail!("Swapchain images must have TRANSFER_DST usage to be cleared");
+        }
+
+        let color: gfx_hal::command::ClearColorRaw = color.into();
+        let range = gfx_hal::image::SubresourceRange {
+            aspects: gfx_hal::format::Aspects::COLOR,
+            levels: 0..1,
+            layers: 0..1,
+        };
+
+        let old_layout = self.image_layout(index);
+        let (old_stage, old_access) = layout_state(old_layout);
+        buffer.pipeline_barrier(
+            old_stage..gfx_hal::pso::PipelineStage::TRANSFER,
+            gfx_hal::memory::Dependencies::empty(),
+            Some(gfx_hal::memory::Barrier::Image {
+                states: (old_access, old_layout)
+                    ..(
+                        gfx_hal::image::Access::TRANSFER_WRITE,
+                        gfx_hal::image::Layout::TransferDstOptimal,
+                    ),
+                families: None,
+                target: image.raw(),
+                range: range.clone(),
+            }),
+        );
+
+        buffer.clear_image(
+            image.raw(),
+            gfx_hal::image::Layout::TransferDstOptimal,
+            color,
+            gfx_hal::command::ClearDepthStencil(1.0, 0).into(),
+            Some(&range),
+        );
+
+        buffer.pipeline_barrier(
+            gfx_hal::pso::PipelineStage::TRANSFER..gfx_hal::pso::PipelineStage::BOTTOM_OF_PIPE,
+            gfx_hal::memory::Dependencies::empty(),
+            Some(gfx_hal::memory::Barrier::Image {
+                states: (
+                    gfx_hal::image::Access::TRANSFER_WRITE,
+                    gfx_hal::image::Layout::TransferDstOptimal,
+                )
+                    ..(
+                        gfx_hal::image::Access::empty(),
+                        gfx_hal::image::Layout::Present,
+                    ),
+                families: None,
+                target: image.raw(),
+                range,
+            }),
+        );
+
+        self.set_image_layout(index, gfx_hal::image::Layout::Present);
+
+        Ok(())
+    }
+
+    /// Record commands that blit `src` image into swapchain image at `index`
+    /// and transition swapchain image from layout returned by [`image_layout`]
+    /// into `Present` layout.
+    /// Image is copied instead if extents and formats match.
+    ///
+    /// `src_extent` is the region of `src` image starting at origin to blit from.
+    /// Formats must match or both be uncompressed color formats.
+    /// Swapchain must be created with `TRANSFER_DST` usage.
+    ///
+    /// This allows to render a frame once and both show and capture it,
+    /// e.g. for streaming. Render into an offscreen image,
+    /// read it back for capture and blit it into the swapchain image.
+    /// Blit takes care of format conversion if capture format differs from swapchain one.
+    ///
+    /// # Safety
+    ///
+    /// `src` image must be in `TransferSrcOptimal` layout
+    /// with all writes to it made available for transfer reads.
+    /// Swapchain image at `index` must be acquired.
+    ///
+    /// [`image_layout`]: #method.image_layout
+    pub unsafe fn blit_from(
+        &self,
+        buffer: &mut impl gfx_hal::command::RawCommandBuffer<B>,
+        index: ImageIndex,
+        src: &Image<B>,
+        src_extent: Extent2D,
+        filter: gfx_hal::image::Filter,
+    ) -> Result<(), failure::Error> {
+        let dst = &self.backbuffer()[index.0 as usize];
+
+        if !self.usage().contains(gfx_hal::image::Usage::TRANSFER_DST) {
+            failure::bail!("Swapchain images must have TRANSFER_DST usage to be blitted into");
+        }
+
+        let is_color = |format: gfx_hal::format::Format| {
+            let desc = format.surface_desc();
+            !desc.is_compressed() && desc.aspects == gfx_hal::format::Aspects::COLOR
+        };
+
+        let same_format = src.format() == dst.format();
+        if !same_format && !(is_color(src.format()) && is_color(dst.format())) {
+            failure::bail!(
+                "Format {:?} can't be blitted into swapchain image format {:?}",
+                src.format(),
+                dst.format()
+            );
+        }
+
+        let range = gfx_hal::image::SubresourceRange {
+            aspects: gfx_hal::format::Aspects::COLOR,
+            levels: 0..1,
+            layers: 0..1,
+        };
+        let layers = gfx_hal::image::SubresourceLayers {
+            aspects: gfx_hal::format::Aspects::COLOR,
+            level: 0,
+            layers: 0..1,
+        };
+
+        let old_layout = self.image_layout(index);
+        let (old_stage, old_access) = layout_state(old_layout);
+        buffer.pipeline_barrier(
+            (old_stage | gfx_hal::pso::PipelineStage::TRANSFER)
+                ..gfx_hal::pso::PipelineStage::TRANSFER,
+            gfx_hal::memory::Dependencies::empty(),
+            Some(gfx_hal::memory::Barrier::Image {
+                states: (old_access, old_layout)
+                    ..(
+                        gfx_hal::image::Access::TRANSFER_WRITE,
+                        gfx_hal::image::Layout::TransferDstOptimal,
+                    ),
+                families: None,
+                target: dst.raw(),
+                range: range.clone(),
+            }),
+        );
+
+        let dst_extent = self.extent();
+        if same_format && src_extent == dst_extent {
+            buffer.copy_image(
+                src.raw(),
+                gfx_hal::image::Layout::TransferSrcOptimal,
+                dst.raw(),
+                gfx_hal::image::Layout::TransferDstOptimal,
+                Some(gfx_hal::command::ImageCopy {
+                    src_subresource: layers.clone(),
+                    src_offset: gfx_hal::image::Offset::ZERO,
+                    dst_subresource: layers,
+                    dst_offset: gfx_hal::image::Offset::ZERO,
+                    extent: dst_extent.to_extent(),
+                }),
+            );
+        } else {
+            buffer.blit_image(
+                src.raw(),
+                gfx_hal::image::Layout::TransferSrcOptimal,
+                dst.raw(),
+                gfx_hal::image::Layout::TransferDstOptimal,
+                filter,
+                Some(gfx_hal::command::ImageBlit {
+                    src_subresource: layers.clone(),
+                    src_bounds: gfx_hal::image::Offset::ZERO.into_bounds(&src_extent.to_extent()),
+                    dst_subresource: layers,
+                    dst_bounds: gfx_hal::image::Offset::ZERO.into_bounds(&dst_extent.to_extent()),
+                }),
+            );
+        }
+
+        buffer.pipeline_barrier(
+            gfx_hal::pso::PipelineStage::TRANSFER..gfx_hal::pso::PipelineStage::BOTTOM_OF_PIPE,
+            gfx_hal::memory::Dependencies::empty(),
+            Some(gfx_hal::memory::Barrier::Image {
+                states: (
+                    gfx_hal::image::Access::TRANSFER_WRITE,
+                    gfx_hal::image::Layout::TransferDstOptimal,
+                )
+                    ..(
+                        gfx_hal::image::Access::empty(),
+                        gfx_hal::image::Layout::Present,
+                    ),
+                families: None,
+                target: dst.raw(),
+                range,
+            }),
+        );
+
+        self.set_image_layout(index, gfx_hal::image::Layout::Present);
+
+        Ok(())
+    }
+
+    /// Get layout of the swapchain image at `index`.
+    ///
+    /// This is the layout image is left in by commands recorded with
+    /// [`clear_image`], [`blit_from`] or reported with [`set_image_layout`].
+    /// Images of newly created swapchain are in `Undefined` layout.
+    /// Presentation engine returns images in `Present` layout,
+    /// so layout of an image that was written before is reset to `Present` when it is acquired again.
+    ///
+    /// [`clear_image`]: #method.clear_image
+    /// [`blit_from`]: #method.blit_from
+    /// [`set_image_layout`]: #method.set_image_layout
+    pub fn image_layout(&self, index: ImageIndex) -> gfx_hal::image::Layout {
+        self.layouts
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())[index.0 as usize]
+    }
+
+    /// Report layout of the swapchain image at `index`
+    /// after recording barriers for it manually.
+    pub fn set_image_layout(&self, index: ImageIndex, layout: gfx_hal::image::Layout) {
+        self.layouts
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())[index.0 as usize] = layout;
+    }
+
+    /// Acquire next image.
+    ///
+    /// Acquisition keeps no per-frame state besides the `signal` semaphore
+    /// provided by the caller. So it may be called at any point of the frame,
+    /// e.g. after all offscreen work is recorded and submitted,
+    /// right before commands that write into the swapchain image.
+    /// This minimizes time between input sampling and presentation.
+    ///
+    /// Note that acquiring late leaves less time to absorb frame time spikes.
+    /// With `Fifo` present mode and few images a late frame misses vertical blank
+    /// and previous image is shown twice, making frame pacing less even.
+    ///
+    /// Acquisition errors should be handled as follows:
+    ///
+    /// * `OutOfDate` - swapchain must be [recreated].
+    /// * `NotReady` - no image is available yet. Never returned by this function
+    ///   as it waits indefinitely. See [`next_image_timeout`].
+    /// * `SurfaceLost` - surface is no longer usable, e.g. display is unplugged.
+    ///   New surface must be created and set with [`recreate_surface`].
+    /// * `DeviceLost` - device must be recreated. See [`mark_device_lost`].
+    /// * `OutOfMemory` - fatal.
+    ///
+    /// Timeout is reported as `NotReady`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if presentation engine returns index of nonexistent image.
+    /// This is a driver bug that can't be recovered from,
+    /// and the same applies to all other acquisition functions.
+    ///
+    /// [recreated]: #method.recreate
+    /// [`next_image_timeout`]: #method.next_image_timeout
+    /// [`recreate_surface`]: #method.recreate_surface
+    /// [`mark_device_lost`]: #method.mark_device_lost
+    pub unsafe fn next_image(
+        &mut self,
+        signal: &B::Semaphore,
+    ) -> Result<NextImages<'_, B>, gfx_hal::AcquireError> {
+        let index = self.acquire_index(signal)?;
+
+        Ok(NextImages {
+            targets: std::iter::once((&*self, index)).collect(),
+        })
+    }
+
+    /// Acquire next image waiting at most `timeout_ns` nanoseconds.
+    ///
+    /// Returns `Ok(None)` if no image became available in time
+    /// (`AcquireError::NotReady` reported by the driver).
+    /// This is not an error. `signal` is left unsignaled
+    /// and render loop may skip the frame and try again later.
+    /// Zero timeout makes this non-blocking.
+    pub unsafe fn next_image_timeout(
+        &mut self,
+        signal: &B::Semaphore,
+        timeout_ns: u64,
+    ) -> Result<Option<NextImages<'_, B>>, gfx_hal::AcquireError> {
+        let index = match not_ready_as_none(self.acquire_index_timeout(signal, timeout_ns))? {
+            Some(index) => index,
+            None => return Ok(None),
+        };
+
+        Ok(Some(NextImages {
+            targets: std::iter::once((&*self, index)).collect(),
+        }))
+    }
+
+    /// Return specified image as if it was acquired, bypassing presentation engine.
+    ///
+    /// Allows to test per-image resource selection and present ordering deterministically.
+    /// Presentation engine is not aware of the acquisition,
+    /// so presenting forced image to real swapchain is invalid.
+    /// Image is tracked the same way as one acquired with [`next_image`].
+    /// Available only in tests or with `test-backend` feature enabled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    ///
+    /// [`next_image`]: #method.next_image
+    #[cfg(any(test, feature = "test-backend"))]
+    pub fn acquire_forced(&mut self, index: ImageIndex) -> NextImages<'_, B> {
+        assert!(
+            (index.0 as usize) < self.backbuffer().len(),
+            "Image index is out of bounds"
+        );
+
+        self.warn_unpresented();
+        self.image_acquired(index);
+
+        NextImages {
+            targets: std::iter::once((&*self, index)).collect(),
+        }
+    }
+
+    /// Acquire next image recreating swapchain if it is out of date.
+    ///
+    /// Swapchain is recreated at most [`max_recreations`] times.
+    /// If it is still out of date, `None` is returned
+    /// and the frame should be skipped. Happens during rapid resizing.
+    ///
+    /// Suboptimal swapchain is not recreated as acquired image is still usable.
+    ///
+    /// New surface requires the window, so lost surface is recovered only if
+    /// `surface_lost` is provided. Its callback creates new surface
+    /// that is set with [`recreate_surface`], and the old surface is disposed.
+    /// Otherwise error is returned and [`recreate_surface`] should be called manually.
+    /// Surface is recreated at most once per call.
+    ///
+    /// # Safety
+    ///
+    /// Same as for [`recreate`].
+    ///
+    /// [`max_recreations`]: #method.set_max_recreations
+    /// [`recreate`]: #method.recreate
+    /// [`recreate_surface`]: #method.recreate_surface
+    pub unsafe fn acquire_with_recovery(
+        &mut self,
+        physical_device: &B::PhysicalDevice,
+        device: &Device<B>,
+        suggest_extent: Extent2D,
+        signal: &B::Semaphore,
+        mut surface_lost: Option<SurfaceRecovery<'_, B>>,
+    ) -> Result<Option<ImageIndex>, failure::Error> {
+        let mut recreations = 0;
+        loop {
+            match self.acquire_index(signal) {
+                Ok(index) => return Ok(Some(index)),
+                Err(gfx_hal::AcquireError::OutOfDate) if recreations < self.max_recreations => {
+                    recreations += 1;
+                    self.recreate(physical_device, device, suggest_extent)?;
+                }
+                Err(gfx_hal::AcquireError::OutOfDate) => {
+                    log::debug!(
+                        "Swapchain is out of date after {} recreations. Skip frame",
+                        recreations
+                    );
+                    return Ok(None);
+                }
+                Err(gfx_hal::AcquireError::SurfaceLost(_)) if surface_lost.is_some() => {
+                    let recovery = surface_lost.take().unwrap();
+                    log::warn!("Surface is lost. Recreate it");
+                    let surface = (recovery.create)(recovery.instance)?;
+                    match self.recreate_surface(
+                        surface,
+                        physical_device,
+                        device,
+                        recovery.family,
+                        suggest_extent,
+                    ) {
+                        Ok(old) => old.dispose(recovery.instance),
+                        Err((surface, err)) => {
+                            surface.dispose(recovery.instance);
+                            return Err(err);
+                        }
+                    }
+                }
+                Err(err @ gfx_hal::AcquireError::SurfaceLost(_)) => {
+                    log::warn!("Surface is lost. It must be recreated with `recreate_surface`");
+                    return Err(err.into());
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    /// Mark target as belonging to the lost device.
+    ///
+    /// Once marked, acquisition fails with `AcquireError::DeviceLost`
+    /// and presentation fails with `PresentError::DeviceLost`
+    /// without calling into the driver.
+    ///
+    /// Device loss invalidates every object created from the device including the swapchain,
+    /// so target can't be recovered by recreating the swapchain.
+    /// Instead application should:
+    ///
+    /// 1. [`dispose`] the target with the lost device to get the `Surface` back.
+    ///    Destroying objects of the lost device is still valid.
+    /// 2. Drop all other objects of the lost device and the device itself.
+    /// 3. Create new device from the same adapter.
+    /// 4. Create new target with [`from_surface`] using the new device.
+    ///
+    /// [`dispose`]: #method.dispose
+    /// [`from_surface`]: #method.from_surface
+    pub fn mark_device_lost(&mut self) {
+        log::warn!("Target is marked as belonging to the lost device");
+        self.device_lost = true;
+    }
+
+    /// Check if target was [marked] as belonging to the lost device.
+    ///
+    /// [marked]: #method.mark_device_lost
+    pub fn is_device_lost(&self) -> bool {
+        self.device_lost
+    }
+
+    /// Set maximal number of swapchain recreations per [`acquire_with_recovery`] call.
+    /// Default is 2.
+    ///
+    /// [`acquire_with_recovery`]: #method.acquire_with_recovery
+    pub fn set_max_recreations(&mut self, max_recreations: u32) {
+        self.max_recreations = max_recreations;
+    }
+
+    /// Acquire next image returning its index together with the image.
+    ///
+    /// Use [`present_index`] to present the image afterwards.
+    ///
+    /// [`present_index`]: #method.present_index
+    pub unsafe fn acquire(
+        &mut self,
+        signal: &B::Semaphore,
+    ) -> Result<(ImageIndex, &Image<B>), gfx_hal::AcquireError> {
+        let index = self.acquire_index(signal)?;
+        let image = &self.backbuffer()[index.0 as usize];
+        Ok((index, image))
+    }
+
+    /// Present image at `index` by the queue without constructing `NextImages`.
+    ///
+    /// Same as presenting `NextImages` with single image, including [frame cap]
+    /// and [before-present callback], but doesn't allocate.
+    ///
+    /// # Safety
+    ///
+    /// Image at `index` must be acquired and not presented yet.
+    /// `queue` must support presentation to the surface.
+    ///
+    /// [frame cap]: #method.set_frame_cap
+    /// [before-present callback]: #method.on_before_present
+    pub unsafe fn present_single<'b>(
+        &self,
+        queue: &mut impl gfx_hal::queue::RawCommandQueue<B>,
+        index: ImageIndex,
+        wait: impl IntoIterator<Item = &'b (impl std::borrow::Borrow<B::Semaphore> + 'b)>,
+    ) -> Result<PresentStatus, gfx_hal::window::PresentError> {
+        assert!(
+            (index.0 as usize) < self.backbuffer().len(),
+            "Image index is out of bounds"
+        );
+
+        if self.device_lost {
+            return Err(gfx_hal::window::PresentError::DeviceLost(
+                gfx_hal::device::DeviceLost,
+            ));
+        }
+
+        self.before_present(index);
+
+        let result = queue.present(
+            std::iter::once((
+                self.swapchain.as_ref().expect("Swapchain already disposed"),
+                index.0,
+            )),
+            wait,
+        );
+
+        result.map(|suboptimal| match suboptimal {
+            Some(gfx_hal::window::Suboptimal) => PresentStatus::Suboptimal,
+            None => PresentStatus::Optimal,
+        })
+    }
+
+    /// Create "render finished" semaphore for each swapchain image.
+    ///
+    /// Semaphores are keyed by image index rather than frame index,
+    /// so semaphore is never signaled again before presentation waited for it.
+    /// They are recreated along with swapchain and destroyed when target is disposed.
+    ///
+    /// See [`render_finished_semaphore`] and [`NextImages::present_render_finished`].
+    ///
+    /// [`render_finished_semaphore`]: #method.render_finished_semaphore
+    /// [`NextImages::present_render_finished`]: struct.NextImages.html#method.present_render_finished
+    pub fn create_render_finished_semaphores(
+        &mut self,
+        device: &Device<B>,
+    ) -> Result<(), gfx_hal::device::OutOfMemory> {
+        self.assert_device_owner(device);
+
+        if self.render_finished.is_none() {
+            self.render_finished = Some(
+                self.backbuffer()
+                    .iter()
+                    .map(|_| device.create_semaphore())
+                    .collect::<Result<_, _>>()?,
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Create image view for each swapchain image.
+    /// Views are owned by the target and recreated along with swapchain,
+    /// [`generation`] changes when it happens so that framebuffers can be rebuilt.
+    /// Does nothing if views are already created.
+    ///
+    /// [`generation`]: #method.generation
+    pub fn create_image_views(&mut self, device: &Device<B>) -> Result<(), failure::Error> {
+        self.assert_device_owner(device);
+
+        if self.image_views.is_none() {
+            let views = unsafe {
+                create_image_views(
+                    device,
+                    self.backbuffer
+                        .as_ref()
+                        .expect("Swapchain already disposed"),
+                    &self.swapchain_config,
+                )
+            }?;
+            self.image_views = Some(views);
+        }
+
+        Ok(())
+    }
+
+    /// Get views of the swapchain images in order of image indices.
+    ///
+    /// # Panics
+    ///
+    /// Panics if views were not [created].
+    ///
+    /// [created]: #method.create_image_views
+    pub fn image_views(&self) -> &[B::ImageView] {
+        self.image_views
+            .as_ref()
+            .expect("Image views are not created")
+    }
+
+    /// Create pool of semaphores used by [`acquire_pooled`].
+    /// Pool size is set by [`TargetConfig::acquire_semaphore_count`].
+    /// Semaphores are destroyed when target is disposed.
+    /// Does nothing if pool is already created.
+    ///
+    /// [`acquire_pooled`]: #method.acquire_pooled
+    /// [`TargetConfig::acquire_semaphore_count`]: struct.TargetConfig.html#structfield.acquire_semaphore_count
+    pub fn create_acquire_semaphores(
+        &mut self,
+        device: &Device<B>,
+    ) -> Result<(), gfx_hal::device::OutOfMemory> {
+        self.assert_device_owner(device);
+
+        if self.acquire_semaphores.is_none() {
+            let image_count = self.image_count();
+            let mut semaphores = AcquireSemaphores {
+                free: Vec::new(),
+                per_image: Vec::new(),
+            };
+            semaphores.reset(
+                device,
+                image_count,
+                acquire_semaphore_pool_size(&self.config, image_count),
+            )?;
+            self.acquire_semaphores = Some(semaphores);
+        }
+
+        Ok(())
+    }
+
+    /// Get number of semaphores in the pool used by [`acquire_pooled`].
+    ///
+    /// [`acquire_pooled`]: #method.acquire_pooled
+    pub fn acquire_semaphore_pool_size(&self) -> u32 {
+        acquire_semaphore_pool_size(&self.config, self.image_count())
+    }
+
+    /// Acquire next image signaling semaphore from the pool.
+    /// Returns index of the image and the semaphore
+    /// that submission rendering into the image should wait for.
+    ///
+    /// Semaphore stays assigned to the image until the image is acquired again.
+    ///
+    /// # Panics
+    ///
+    /// Panics if pool was not [created].
+    ///
+    /// [created]: #method.create_acquire_semaphores
+    pub unsafe fn acquire_pooled(
+        &mut self,
+    ) -> Result<(ImageIndex, &B::Semaphore), gfx_hal::AcquireError> {
+        // Pool is larger than number of images, so at least one semaphore is always free.
+        let semaphore = self
+            .acquire_semaphores
+            .as_mut()
+            .expect("Acquire semaphores are not created")
+            .free
+            .pop()
+            .expect("Acquire semaphore pool is exhausted");
+
+        let result = self.acquire_index(&semaphore);
+        let semaphores = self.acquire_semaphores.as_mut().unwrap();
+        let index = match result {
+            Ok(index) => index,
+            Err(err) => {
+                semaphores.free.push(semaphore);
+                return Err(err);
+            }
+        };
+
+        let slot = &mut semaphores.per_image[index.0 as usize];
+        if let Some(previous) = slot.replace(semaphore) {
+            semaphores.free.push(previous);
+        }
+
+        let semaphore = semaphores.per_image[index.0 as usize].as_ref().unwrap();
+        Ok((index, semaphore))
+    }
+
+    /// Create render pass with single subpass writing
+    /// single color attachment of the swapchain format.
+    ///
+    /// Attachment is transitioned into `Present` layout at the end of the render pass,
+    /// so no barrier is needed before presentation.
+    /// Initial layout is `Undefined` unless `load_op` is `Load`,
+    /// in which case image is expected to be in `Present` layout.
+    ///
+    /// Render pass must be recreated when swapchain format changes,
+    /// see [`RecreateOutcome::format_changed`].
+    ///
+    /// [`RecreateOutcome::format_changed`]: struct.RecreateOutcome.html#method.format_changed
+    pub unsafe fn create_compatible_render_pass(
+        &self,
+        device: &Device<B>,
+        load_op: gfx_hal::pass::AttachmentLoadOp,
+    ) -> Result<B::RenderPass, failure::Error> {
+        self.assert_device_owner(device);
+
+        let initial_layout = match load_op {
+            gfx_hal::pass::AttachmentLoadOp::Load => gfx_hal::image::Layout::Present,
+            _ => gfx_hal::image::Layout::Undefined,
+        };
+
+        let attachment = gfx_hal::pass::Attachment {
+            format: Some(self.format()),
+            samples: 1,
+            ops: gfx_hal::pass::AttachmentOps {
+                load: load_op,
+                store: gfx_hal::pass::AttachmentStoreOp::Store,
+            },
+            stencil_ops: gfx_hal::pass::AttachmentOps::DONT_CARE,
+            layouts: initial_layout..gfx_hal::image::Layout::Present,
+        };
+
+        let subpass = gfx_hal::pass::SubpassDesc {
+            colors: &[(0, gfx_hal::image::Layout::ColorAttachmentOptimal)],
+            depth_stencil: None,
+            inputs: &[],
+            resolves: &[],
+            preserves: &[],
+        };
+
+        // Wait for image acquisition which is expected to be waited for at this stage.
+        let dependency = gfx_hal::pass::SubpassDependency {
+            passes: gfx_hal::pass::SubpassRef::External..gfx_hal::pass::SubpassRef::Pass(0),
+            stages: self.acquire_wait_stage..gfx_hal::pso::PipelineStage::COLOR_ATTACHMENT_OUTPUT,
+            accesses: gfx_hal::image::Access::empty()
+                ..(gfx_hal::image::Access::COLOR_ATTACHMENT_READ
+                    | gfx_hal::image::Access::COLOR_ATTACHMENT_WRITE),
+        };
+
+        let render_pass =
+            device.create_render_pass(Some(attachment), Some(subpass), Some(dependency))?;
+
+        Ok(render_pass)
+    }
+
+    /// Set pipeline stage at which submissions writing into swapchain image
+    /// wait for acquisition semaphore.
+    /// Default is `COLOR_ATTACHMENT_OUTPUT`.
+    ///
+    /// Presentation itself waits for semaphores without stage in gfx-hal,
+    /// so the stage matters only for submissions.
+    /// Use `COMPUTE_SHADER` when image is written by compute shaders
+    /// to avoid over-synchronization.
+    /// Render passes created with [`create_compatible_render_pass`] depend on this stage.
+    ///
+    /// [`create_compatible_render_pass`]: #method.create_compatible_render_pass
+    pub fn set_acquire_wait_stage(&mut self, stage: gfx_hal::pso::PipelineStage) {
+        self.acquire_wait_stage = stage;
+    }
+
+    /// Get pipeline stage set with [`set_acquire_wait_stage`].
+    /// Use it in `wait_semaphores` of submissions that wait for acquisition.
+    ///
+    /// [`set_acquire_wait_stage`]: #method.set_acquire_wait_stage
+    pub fn acquire_wait_stage(&self) -> gfx_hal::pso::PipelineStage {
+        self.acquire_wait_stage
+    }
+
+    /// Create framebuffer for each swapchain image in order of image indices.
+    ///
+    /// Attachments of each framebuffer are view of the swapchain image
+    /// [created] by the target followed by `extra_attachments` shared by all framebuffers,
+    /// e.g. depth buffer view.
+    /// Framebuffers are sized to [`extent`].
+    ///
+    /// Framebuffers must be destroyed and created again when [`generation`] changes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if image views were not [created].
+    ///
+    /// [created]: #method.create_image_views
+    /// [`extent`]: #method.extent
+    /// [`generation`]: #method.generation
+    pub unsafe fn create_framebuffers(
+        &self,
+        device: &Device<B>,
+        render_pass: &B::RenderPass,
+        extra_attachments: &[&B::ImageView],
+    ) -> Result<Vec<B::Framebuffer>, failure::Error> {
+        self.assert_device_owner(device);
+
+        let extent = self.extent();
+        let framebuffers = self
+            .image_views()
+            .iter()
+            .map(|view| {
+                device.create_framebuffer(
+                    render_pass,
+                    std::iter::once(view).chain(extra_attachments.iter().cloned()),
+                    gfx_hal::image::Extent {
+                        width: extent.width,
+                        height: extent.height,
+                        depth: 1,
+                    },
+                )
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(framebuffers)
+    }
+
+    /// Get "render finished" semaphore for swapchain image at `index`.
+    /// Submission that renders into the image should signal it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if semaphores were not [created].
+    ///
+    /// [created]: #method.create_render_finished_semaphores
+    pub fn render_finished_semaphore(&self, index: ImageIndex) -> &B::Semaphore {
+        &self
+            .render_finished
+            .as_ref()
+            .expect("Render finished semaphores are not created")[index.0 as usize]
+    }
+
+    /// Get `NextImages` for the image acquired with [`acquire`]
+    /// to present it.
+    ///
+    /// `index` must be acquired and not presented yet.
+    ///
+    /// [`acquire`]: #method.acquire
+    pub fn present_index(&self, index: ImageIndex) -> NextImages<'_, B> {
+        assert!(
+            (index.0 as usize) < self.backbuffer().len(),
+            "Image index is out of bounds"
+        );
+
+        NextImages {
+            targets: std::iter::once((self, index)).collect(),
+        }
+    }
+
+    /// Acquire next image and wait until it is no longer in flight.
+    ///
+    /// If a fence was [marked] for the acquired image
+    /// this function waits for the fence before returning.
+    /// This prevents rendering into the image that is still used by previous submissions.
+    ///
+    /// [marked]: #method.mark_in_flight
+    pub unsafe fn acquire_next(
+        &mut self,
+        device: &Device<B>,
+        signal: &B::Semaphore,
+    ) -> Result<NextImages<'_, B>, gfx_hal::AcquireError> {
+        self.assert_device_owner(device);
+
+        if self.device_lost {
+            return Err(gfx_hal::AcquireError::DeviceLost(
+                gfx_hal::device::DeviceLost,
+            ));
+        }
+
+        if let Some(limit) = self.cpu_ahead_limit {
+            while self.mark_order.len() as u64 > limit {
+                let oldest = self.mark_order.pop_front().unwrap();
+                if let Some(fence) = &self.images_in_flight[oldest as usize] {
+                    device.wait_for_fence(fence, !0).map_err(acquire_error)?;
+                }
+            }
+        }
+
+        let index = self.acquire_index(signal)?;
+
+        if let Some(fence) = &self.images_in_flight[index.0 as usize] {
+            device.wait_for_fence(fence, !0).map_err(acquire_error)?;
+        }
+
+        Ok(NextImages {
+            targets: std::iter::once((&*self, index)).collect(),
+        })
+    }
+
+    /// Mark image at `index` as used by submission that signals `fence`.
+    /// [`acquire_next`] will wait for the fence when this image is acquired again.
+    ///
+    /// Returns fence previously marked for this image.
+    /// It is signaled if the image was acquired with [`acquire_next`] since then
+    /// and can be reused.
+    ///
+    /// Marked fences are owned by the target.
+    /// When swapchain is recreated they are retired and destroyed by [`collect_garbage`]
+    /// once signaled. Remaining fences are destroyed when target is disposed.
+    ///
+    /// [`acquire_next`]: #method.acquire_next
+    /// [`collect_garbage`]: #method.collect_garbage
+    pub fn mark_in_flight(&mut self, index: ImageIndex, fence: B::Fence) -> Option<B::Fence> {
+        let ImageIndex(index) = index;
+        self.mark_order.retain(|&marked| marked != index);
+        self.mark_order.push_back(index);
+        if let Some(guarded) = &mut self.guarded {
+            guarded[index as usize] = true;
+        }
+        self.images_in_flight[index as usize].replace(fence)
+    }
+
+    /// Enable validation of presentation synchronization in debug builds.
+    ///
+    /// When enabled, presenting image for which no fence was [marked]
+    /// since it was acquired logs an error. This usually means that
+    /// presentation is not synchronized with rendering into the image.
+    /// Only images acquired through the target are tracked.
+    ///
+    /// Validation does nothing in release builds.
+    ///
+    /// [marked]: #method.mark_in_flight
+    pub fn set_present_validation(&mut self, enabled: bool) {
+        self.guarded = if enabled && cfg!(debug_assertions) {
+            Some(self.images_in_flight.iter().map(|_| true).collect())
+        } else {
+            None
+        };
+    }
+
+    /// Limit number of frames CPU may run ahead of GPU.
+    ///
+    /// [`acquire_next`] waits until no more than `frames` of
+    /// most recently [marked] fences are pending before acquiring next image.
+    /// Zero makes each frame wait for all previous frames to complete.
+    ///
+    /// `None` removes the limit. Without the limit frames in flight
+    /// are bound only by number of swapchain images.
+    ///
+    /// [`acquire_next`]: #method.acquire_next
+    /// [marked]: #method.mark_in_flight
+    pub fn set_cpu_ahead_limit(&mut self, frames: Option<u64>) {
+        self.cpu_ahead_limit = frames;
+    }
+
+    /// Bind queue family used to present images of this target.
+    /// Fails if the family can't present to the surface,
+    /// or if it doesn't support graphics unless [allowed].
+    ///
+    /// Raw queues are borrowed mutably for submission,
+    /// so target stores only family of the present queue
+    /// and presentation functions still take the queue explicitly.
+    /// [`NextImages::present_on_device`] checks that the queue is of the bound family.
+    ///
+    /// [allowed]: #method.set_allow_non_graphics_present
+    /// [`NextImages::present_on_device`]: struct.NextImages.html#method.present_on_device
+    pub fn bind_present_queue(&mut self, family: &B::QueueFamily) -> Result<(), failure::Error> {
+        if !gfx_hal::Surface::supports_queue_family(self.surface.raw(), family) {
+            failure::bail!("Queue family can't present to the surface");
+        }
+        if !self.allow_non_graphics_present
+            && !gfx_hal::queue::QueueFamily::supports_graphics(family)
+        {
+            failure::bail!(
+                "Queue family doesn't support graphics. Presentation from compute or transfer families must be allowed explicitly"
+            );
+        }
+        self.present_family = Some(gfx_hal::queue::QueueFamily::id(family));
+        Ok(())
+    }
+
+    /// Switch present mode to `Fifo` on [`recreate`] while machine is on battery.
+    ///
+    /// `is_on_battery` is provided by application and queried each time swapchain is recreated.
+    /// Only `Mailbox` and `Immediate` modes are downgraded.
+    /// Requested present mode is kept in the [`config`]
+    /// and restored by first recreation after machine is no longer on battery.
+    /// `None` disables the downgrade.
+    ///
+    /// [`recreate`]: #method.recreate
+    /// [`config`]: #method.config
+    pub fn set_power_aware_vsync(
+        &mut self,
+        is_on_battery: Option<Box<dyn Fn() -> bool + Send + Sync>>,
+    ) {
+        self.is_on_battery = is_on_battery;
+    }
+
+    /// Allow binding present queue of the family that doesn't support graphics,
+    /// e.g. compute-only family on hardware that can present from it.
+    /// Presentation support is validated by [`bind_present_queue`] either way.
+    ///
+    /// [`bind_present_queue`]: #method.bind_present_queue
+    pub fn set_allow_non_graphics_present(&mut self, allow: bool) {
+        self.allow_non_graphics_present = allow;
+    }
+
+    /// Get index of the image acquired last.
+    /// Reset when swapchain is recreated.
+    pub fn last_acquired(&self) -> Option<ImageIndex> {
+        self.last_acquired
+    }
+
+    /// Get index of the image presented last.
+    /// Reset when swapchain is recreated.
+    ///
+    /// Differs from [`last_acquired`] if acquired image is not presented yet.
+    /// Acquiring images without presenting them eventually
+    /// leaves no images to acquire.
+    ///
+    /// [`last_acquired`]: #method.last_acquired
+    pub fn last_presented(&self) -> Option<ImageIndex> {
+        *self
+            .last_presented
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Get family of the queue bound with [`bind_present_queue`].
+    ///
+    /// [`bind_present_queue`]: #method.bind_present_queue
+    pub fn present_queue_family(&self) -> Option<gfx_hal::queue::QueueFamilyId> {
+        self.present_family
+    }
+
+    unsafe fn acquire_index(
+        &mut self,
+        signal: &B::Semaphore,
+    ) -> Result<ImageIndex, gfx_hal::AcquireError> {
+        self.acquire_index_timeout(signal, !0)
+    }
+
+    unsafe fn acquire_index_timeout(
+        &mut self,
+        signal: &B::Semaphore,
+        timeout_ns: u64,
+    ) -> Result<ImageIndex, gfx_hal::AcquireError> {
+        if self.device_lost {
+            return Err(gfx_hal::AcquireError::DeviceLost(
+                gfx_hal::device::DeviceLost,
+            ));
+        }
+
+        self.warn_unpresented();
+
+        let index = gfx_hal::Swapchain::acquire_image(
+            // Missing swapchain is equivalent to OutOfDate, as it has to be recreated anyway.
+            self.swapchain
+                .as_mut()
+                .ok_or(gfx_hal::AcquireError::OutOfDate)?,
+            timeout_ns,
+            Some(signal),
+            None,
+        )?
+        .0;
+
+        let image_count = self.backbuffer().len();
+        assert!(
+            (index as usize) < image_count,
+            "Driver returned image index {} but swapchain has {} images",
+            index,
+            image_count
+        );
+
+        self.image_acquired(ImageIndex(index));
+        Ok(ImageIndex(index))
+    }
+
+    /// Warn in debug builds if previously acquired image is not presented yet.
+    fn warn_unpresented(&self) {
+        // Holding several acquired images at once is valid as long as the
+        // surface allows it, so this is only a hint for the usual one image per frame loop.
+        if cfg!(debug_assertions)
+            && self.last_acquired.is_some()
+            && self.last_acquired != self.last_presented()
+        {
+            log::warn!(
+                "Image {:?} is acquired but not presented before next acquisition",
+                self.last_acquired
+            );
+        }
+    }
+
+    /// Update tracking of the image that was just acquired.
+    fn image_acquired(&mut self, index: ImageIndex) {
+        if let Some(guarded) = &mut self.guarded {
+            guarded[index.0 as usize] = false;
+        }
+
+        let layout = &mut self
+            .layouts
+            .get_mut()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())[index.0 as usize];
+        if *layout != gfx_hal::image::Layout::Undefined {
+            *layout = gfx_hal::image::Layout::Present;
+        }
+
+        self.last_acquired = Some(index);
+    }
+}
+
+/// Stages and accesses that must complete before swapchain image
+/// in specified layout is transitioned into another one.
+fn layout_state(
+    layout: gfx_hal::image::Layout,
+) -> (gfx_hal::pso::PipelineStage, gfx_hal::image::Access) {
+    use gfx_hal::{image::Access, image::Layout, pso::PipelineStage};
+
+    match layout {
+        Layout::Undefined | Layout::Present => (PipelineStage::TOP_OF_PIPE, Access::empty()),
+        Layout::ColorAttachmentOptimal => (
+            PipelineStage::COLOR_ATTACHMENT_OUTPUT,
+            Access::COLOR_ATTACHMENT_WRITE,
+        ),
+        Layout::TransferDstOptimal => (PipelineStage::TRANSFER, Access::TRANSFER_WRITE),
+        _ => (
+            PipelineStage::COLOR_ATTACHMENT_OUTPUT
+                | PipelineStage::FRAGMENT_SHADER
+                | PipelineStage::COMPUTE_SHADER
+                | PipelineStage::TRANSFER,
+            Access::COLOR_ATTACHMENT_WRITE | Access::SHADER_WRITE | Access::TRANSFER_WRITE,
+        ),
+    }
+}
+
+/// Means to replace lost surface in [`Target::acquire_with_recovery`].
+///
+/// [`Target::acquire_with_recovery`]: struct.Target.html#method.acquire_with_recovery
+#[derive(derivative::Derivative)]
+#[derivative(Debug)]
+pub struct SurfaceRecovery<'a, B: Backend> {
+    /// Instance the target's surface is created from.
+    pub instance: &'a Instance<B>,
+
+    /// Queue family used for presentation.
+    #[derivative(Debug = "ignore")]
+    pub family: &'a B::QueueFamily,
+
+    /// Create new surface for the window, e.g. with [`Surface::try_new`].
+    ///
+    /// [`Surface::try_new`]: struct.Surface.html#method.try_new
+    #[derivative(Debug = "ignore")]
+    pub create: &'a mut dyn FnMut(&Instance<B>) -> Result<Surface<B>, failure::Error>,
+}
+
+/// Changes made when swapchain was recreated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RecreateOutcome {
+    /// Present mode of the previous swapchain.
+    pub old_present_mode: gfx_hal::PresentMode,
+
+    /// Present mode of the new swapchain.
+    pub present_mode: gfx_hal::PresentMode,
+
+    /// Extent of the previous swapchain images.
+    pub old_extent: Extent2D,
+
+    /// Extent of the new swapchain images.
+    pub extent: Extent2D,
+
+    /// Format of the previous swapchain images.
+    pub old_format: gfx_hal::format::Format,
+
+    /// Format of the new swapchain images.
+    pub format: gfx_hal::format::Format,
+
+    /// [Generation] of the new swapchain.
+    ///
+    /// [Generation]: struct.Target.html#method.generation
+    pub generation: u64,
+}
+
+impl RecreateOutcome {
+    /// Check if present mode was changed after recreation.
+    pub fn present_mode_changed(&self) -> bool {
+        self.old_present_mode != self.present_mode
+    }
+
+    /// Check if extent was changed after recreation.
+    pub fn extent_changed(&self) -> bool {
+        self.old_extent != self.extent
+    }
+
+    /// Check if format was changed after recreation.
+    pub fn format_changed(&self) -> bool {
+        self.old_format != self.format
+    }
+}
+
+/// Swapchain parameters negotiated with the surface.
+///
+/// Color space and pre-transform are not included, see [backend limitations].
+///
+/// [backend limitations]: index.html#backend-limitations
+#[derive(Clone, Debug)]
+pub struct TargetDescription {
+    /// Name of the backend.
+    pub backend: &'static str,
+
+    /// Format of the swapchain images.
+    pub format: gfx_hal::format::Format,
+
+    /// Present mode of the swapchain.
+    pub present_mode: gfx_hal::PresentMode,
+
+    /// Composite alpha mode of the swapchain.
+    pub composite_alpha: gfx_hal::window::CompositeAlpha,
+
+    /// Number of images actually created.
+    /// May be greater than requested.
+    pub image_count: u32,
+
+    /// Usage of the swapchain images.
+    pub usage: gfx_hal::image::Usage,
+
+    /// Extent of the swapchain images.
+    pub extent: Extent2D,
+}
+
+impl std::fmt::Display for TargetDescription {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            fmt,
+            "backend: {}, format: {:?}, present mode: {:?}, composite alpha: {:?}, images: {}, usage: {:?}, extent: {}x{}",
+            self.backend,
+            self.format,
+            self.present_mode,
+            self.composite_alpha,
+            self.image_count,
+            self.usage,
+            self.extent.width,
+            self.extent.height,
+        )
+    }
+}
+
+/// Backend supported by rendy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BackendKind {
+    /// Empty backend.
+    Empty,
+
+    /// DirectX 12 backend.
+    Dx12,
+
+    /// Metal backend.
+    Metal,
+
+    /// Vulkan backend.
+    Vulkan,
+}
+
+impl BackendKind {
+    /// Get name of the backend.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            BackendKind::Empty => "empty",
+            BackendKind::Dx12 => "dx12",
+            BackendKind::Metal => "metal",
+            BackendKind::Vulkan => "vulkan",
+        }
+    }
+}
+
+/// Get backend of the instance.
+/// Returns `None` if instance is of backend not enabled in rendy.
+pub fn instance_backend<B: Backend>(instance: &Instance<B>) -> Option<BackendKind> {
+    let instance = instance.raw();
+    rendy_with_empty_backend!(if instance.is::<rendy_util::empty::Instance>() {
+        return Some(BackendKind::Empty);
+    });
+    rendy_with_dx12_backend!(if instance.is::<rendy_util::dx12::Instance>() {
+        return Some(BackendKind::Dx12);
+    });
+    rendy_with_metal_backend!(if instance.is::<rendy_util::metal::Instance>() {
+        return Some(BackendKind::Metal);
+    });
+    rendy_with_vulkan_backend!(if instance.is::<rendy_util::vulkan::Instance>() {
+        return Some(BackendKind::Vulkan);
+    });
+
+    None
+}
+
+/// Get backend `B` if it is enabled in rendy.
+fn backend_kind<B: Backend>() -> Option<BackendKind> {
+    use std::any::TypeId;
+
+    rendy_with_empty_backend!(
+        if TypeId::of::<B>() == TypeId::of::<rendy_util::empty::Backend>() {
+            return Some(BackendKind::Empty);
+        }
+    );
+    rendy_with_dx12_backend!(
+        if TypeId::of::<B>() == TypeId::of::<rendy_util::dx12::Backend>() {
+            return Some(BackendKind::Dx12);
+        }
+    );
+    rendy_with_metal_backend!(
+        if TypeId::of::<B>() == TypeId::of::<rendy_util::metal::Backend>() {
+            return Some(BackendKind::Metal);
+        }
+    );
+    rendy_with_vulkan_backend!(
+        if TypeId::of::<B>() == TypeId::of::<rendy_util::vulkan::Backend>() {
+            return Some(BackendKind::Vulkan);
+        }
+    );
+
+    None
+}
+
+/// Convert fence wait error into acquisition error.
+fn acquire_error(err: gfx_hal::device::OomOrDeviceLost) -> gfx_hal::AcquireError {
+    match err {
+        gfx_hal::device::OomOrDeviceLost::OutOfMemory(err) => {
+            gfx_hal::AcquireError::OutOfMemory(err)
+        }
+        gfx_hal::device::OomOrDeviceLost::DeviceLost(err) => gfx_hal::AcquireError::DeviceLost(err),
+    }
+}
+
+/// Index of the swapchain image.
+///
+/// Distinct from index of the frame in flight (see `rendy_frame::FrameIndex`)
+/// which is not related to the order in which images are acquired.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ImageIndex(pub u32);
+
+impl From<u32> for ImageIndex {
+    fn from(index: u32) -> Self {
+        ImageIndex(index)
+    }
+}
+
+impl From<ImageIndex> for u32 {
+    fn from(index: ImageIndex) -> Self {
+        index.0
+    }
+}
+
+/// Identifies swapchain among ones acquired into [`NextImages`].
+/// Equals to position of the swapchain in [`NextImages::indices`].
+///
+/// [`NextImages`]: struct.NextImages.html
+/// [`NextImages::indices`]: struct.NextImages.html#method.indices
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SwapchainId(usize);
+
+impl SwapchainId {
+    /// Get position of the swapchain in [`NextImages`].
+    ///
+    /// [`NextImages`]: struct.NextImages.html
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+/// Status of successful presentation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PresentStatus {
+    /// Swapchain matches the surface.
+    Optimal,
+
+    /// Swapchain no longer matches the surface exactly
+    /// but still can be used for presentation.
+    /// It should be recreated when convenient.
+    Suboptimal,
+}
+
+/// Represents acquire frames that will be presented next.
+#[derive(Debug)]
+pub struct NextImages<'a, B: Backend> {
+    targets: smallvec::SmallVec<[(&'a Target<B>, ImageIndex); 8]>,
+}
+
+impl<'a, B> NextImages<'a, B>
+where
+    B: Backend,
+{
+    /// Get indices.
+    pub fn indices(&self) -> impl IntoIterator<Item = ImageIndex> + '_ {
+        self.targets.iter().map(|(_s, i)| *i)
+    }
+
+    /// Get identifiers of the swapchains in the same order as [`indices`].
+    ///
+    /// [`indices`]: #method.indices
+    pub fn ids(&self) -> impl IntoIterator<Item = SwapchainId> + '_ {
+        (0..self.targets.len()).map(SwapchainId)
+    }
+
+    /// Check if there are no images to present.
+    pub fn is_empty(&self) -> bool {
+        self.targets.is_empty()
+    }
+
+    /// Move images acquired from other targets into this one
+    /// so they are presented together.
+    ///
+    /// Useful when rendering into multiple windows.
+    /// Targets that failed to acquire an image can be simply left out.
+    ///
+    /// # Panics
+    ///
+    /// Panics if targets belong to different devices.
+    pub fn append(&mut self, other: NextImages<'a, B>) {
+        if let (Some(&(first, _)), Some(&(other_first, _))) =
+            (self.targets.first(), other.targets.first())
+        {
+            assert_eq!(
+                first.device, other_first.device,
+                "Images of targets from different devices can't be presented together"
+            );
+        }
+        self.targets.extend(other.targets);
+    }
+
+    /// Present images by the queue.
+    ///
+    /// Sleeps before presentation if any target has [frame cap] set
+    /// and invokes [before-present callbacks].
+    ///
+    /// Does nothing and returns `Ok(None)` if there are no images to present,
+    /// e.g. when all targets failed to acquire image.
+    /// Backend is not invoked in that case.
+    ///
+    /// Presented image is returned to presentation engine
+    /// and must be acquired again before it can be presented once more.
+    /// Presentation engine keeps showing last presented image until next one is presented,
+    /// so applications that render on demand can skip both acquisition and presentation
+    /// when nothing changed.
+    ///
+    /// `wait` may be empty if caller synchronized rendering with presentation otherwise,
+    /// e.g. by waiting for a fence. In debug builds a warning is logged
+    /// if `wait` is empty and no fence is [marked] for a presented image,
+    /// as this is likely a missing semaphore.
+    /// Targets with [present validation] enabled report this as an error instead.
+    ///
+    /// # TODO
+    ///
+    /// Use specific presentation error type.
+    ///
+    /// [frame cap]: struct.Target.html#method.set_frame_cap
+    /// [before-present callbacks]: struct.Target.html#method.on_before_present
+    /// [marked]: struct.Target.html#method.mark_in_flight
+    /// [present validation]: struct.Target.html#method.set_present_validation
+    pub unsafe fn present<'b>(
+        self,
+        queue: &mut impl gfx_hal::queue::RawCommandQueue<B>,
+        wait: impl IntoIterator<Item = &'b (impl std::borrow::Borrow<B::Semaphore> + 'b)>,
+    ) -> Result<Option<gfx_hal::window::Suboptimal>, gfx_hal::window::PresentError>
+    where
+        'a: 'b,
+    {
+        present_images(&self.targets, queue, wait)
+    }
+
+    /// Present images by the queue of the `device`.
+    ///
+    /// Same as [`present`] but in debug builds checks that all targets are created
+    /// with the `device` and that `family` is the one bound to targets with [`bind_present_queue`].
+    /// Raw queues don't know their device and family,
+    /// so `device` and `family` must be the ones `queue` belongs to.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds panics if any target is created with another device
+    /// or has another present queue family bound.
+    ///
+    /// [`present`]: #method.present
+    /// [`bind_present_queue`]: struct.Target.html#method.bind_present_queue
+    pub unsafe fn present_on_device<'b>(
+        self,
+        device: &Device<B>,
+        family: gfx_hal::queue::QueueFamilyId,
+        queue: &mut impl gfx_hal::queue::RawCommandQueue<B>,
+        wait: impl IntoIterator<Item = &'b (impl std::borrow::Borrow<B::Semaphore> + 'b)>,
+    ) -> Result<Option<gfx_hal::window::Suboptimal>, gfx_hal::window::PresentError>
+    where
+        'a: 'b,
+    {
+        for (target, _) in &self.targets {
+            debug_assert_eq!(
+                target.device_id(),
+                device.id(),
+                "Presenting by queue of another device than target is created with"
+            );
+            if let Some(bound) = target.present_family {
+                debug_assert_eq!(
+                    bound, family,
+                    "Presenting by queue of family {:?}, but target has present queue of family {:?} bound",
+                    family, bound
+                );
+            }
+        }
+
+        self.present(queue, wait)
+    }
+
+    /// Present images by the queue waiting on
+    /// [render finished semaphores] for presented images.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any target has no render finished semaphores.
+    ///
+    /// [render finished semaphores]: struct.Target.html#method.render_finished_semaphore
+    pub unsafe fn present_render_finished(
+        self,
+        queue: &mut impl gfx_hal::queue::RawCommandQueue<B>,
+    ) -> Result<Option<gfx_hal::window::Suboptimal>, gfx_hal::window::PresentError> {
+        let wait: smallvec::SmallVec<[&'a B::Semaphore; 8]> = self
+            .targets
+            .iter()
+            .map(|&(target, index)| target.render_finished_semaphore(index))
+            .collect();
+
+        self.present(queue, &wait)
+    }
+
+    /// Present images by the queue one by one.
+    ///
+    /// Unlike [`present`] failure to present to one swapchain
+    /// doesn't prevent presentation to others.
+    /// Result is returned for each swapchain along with its [`SwapchainId`]
+    /// in the same order as [`indices`].
+    ///
+    /// `wait` must yield one semaphore for each swapchain in the same order.
+    /// Each presentation waits only for its own semaphore.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `wait` doesn't yield exactly one semaphore per swapchain.
+    ///
+    /// [`present`]: #method.present
+    /// [`indices`]: #method.indices
+    /// [`SwapchainId`]: struct.SwapchainId.html
+    pub unsafe fn present_available<'b>(
+        self,
+        queue: &mut impl gfx_hal::queue::RawCommandQueue<B>,
+        wait: impl IntoIterator<Item = &'b (impl std::borrow::Borrow<B::Semaphore> + 'b)>,
+    ) -> Vec<(
+        SwapchainId,
+        Result<PresentStatus, gfx_hal::window::PresentError>,
+    )>
+    where
+        'a: 'b,
+    {
+        let wait: smallvec::SmallVec<[_; 8]> = wait.into_iter().collect();
+        assert_eq!(
+            wait.len(),
+            self.targets.len(),
+            "One semaphore per swapchain is expected"
+        );
+
+        self.targets
+            .iter()
+            .zip(wait)
+            .enumerate()
+            .map(|(id, ((target, index), wait))| {
+                if target.device_lost {
+                    return (
+                        SwapchainId(id),
+                        Err(gfx_hal::window::PresentError::DeviceLost(
+                            gfx_hal::device::DeviceLost,
+                        )),
+                    );
+                }
+                target.before_present(*index);
+                let result = queue.present(
+                    std::iter::once((
+                        target
+                            .swapchain
+                            .as_ref()
+                            .expect("Swapchain already disposed"),
+                        index.0,
+                    )),
+                    std::iter::once(wait),
+                );
+                let status = result.map(|suboptimal| match suboptimal {
+                    Some(gfx_hal::window::Suboptimal) => PresentStatus::Suboptimal,
+                    None => PresentStatus::Optimal,
+                });
+                (SwapchainId(id), status)
+            })
+            .collect()
+    }
+
+    /// Present images by different queues.
+    ///
+    /// Each element of `presentations` is a queue,
+    /// swapchains to present by that queue and semaphores to wait for.
+    /// Useful when displays are driven by different queues or devices.
+    /// Result is returned for each element in the same order.
+    ///
+    /// Each element is presented the same way as with [`present`].
+    /// Elements without swapchains are skipped and `Ok(None)` is returned for them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any swapchain is not presented by exactly one queue.
+    ///
+    /// [`present`]: #method.present
+    pub unsafe fn present_split<'b, Q>(
+        self,
+        presentations: &mut [(&mut Q, &[SwapchainId], &[&'b B::Semaphore])],
+    ) -> Vec<Result<Option<gfx_hal::window::Suboptimal>, gfx_hal::window::PresentError>>
+    where
+        'a: 'b,
+        Q: gfx_hal::queue::RawCommandQueue<B>,
+    {
+        let mut presented: smallvec::SmallVec<[bool; 8]> =
+            smallvec::smallvec![false; self.targets.len()];
+        for (_, ids, _) in presentations.iter() {
+            for id in ids.iter() {
+                assert!(
+                    !std::mem::replace(&mut presented[id.0], true),
+                    "Swapchain {:?} is presented more than once",
+                    id
+                );
+            }
+        }
+        assert!(
+            presented.iter().all(|&presented| presented),
+            "All swapchains must be presented"
+        );
+
+        presentations
+            .iter_mut()
+            .map(|(queue, ids, wait)| {
+                let images: smallvec::SmallVec<[_; 8]> =
+                    ids.iter().map(|id| self.targets[id.0]).collect();
+                present_images(&images, &mut **queue, wait.iter())
+            })
+            .collect()
+    }
+
+    /// Present images by the queue and signal `fence`.
+    ///
+    /// gfx-hal has no fence-on-present semantics,
+    /// so `fence` is signaled by an empty submission following presentation.
+    /// It becomes signaled after all work submitted to the queue before presentation is complete,
+    /// which may happen before presentation engine is done with the images.
+    ///
+    /// Fence is submitted even if presentation fails.
+    ///
+    /// # Safety
+    ///
+    /// `fence` must be unsignaled and must not be used by pending submissions.
+    pub unsafe fn present_signal_fence<'b>(
         self,
         queue: &mut impl gfx_hal::queue::RawCommandQueue<B>,
         wait: impl IntoIterator<Item = &'b (impl std::borrow::Borrow<B::Semaphore> + 'b)>,
+        fence: &B::Fence,
     ) -> Result<Option<gfx_hal::window::Suboptimal>, gfx_hal::window::PresentError>
     where
         'a: 'b,
     {
-        queue.present(
-            self.targets.iter().map(|(target, index)| {
-                (
-                    target
-                        .swapchain
-                        .as_ref()
-                        .expect("Swapchain already disposed"),
-                    *index,
-                )
-            }),
-            wait,
-        )
+        let device_lost = self.targets.iter().any(|(target, _)| target.device_lost);
+        let result = self.present(&mut *queue, wait);
+        if device_lost {
+            return result;
+        }
+
+        queue.submit(
+            gfx_hal::queue::Submission {
+                command_buffers: std::iter::empty::<&B::CommandBuffer>(),
+                wait_semaphores: std::iter::empty::<(&B::Semaphore, _)>(),
+                signal_semaphores: std::iter::empty::<&B::Semaphore>(),
+            },
+            Some(fence),
+        );
+
+        result
+    }
+}
+
+/// Present acquired images by the queue.
+///
+/// Does nothing and returns `Ok(None)` if there are no images to present.
+/// Warns in debug builds if images are presented without any synchronization.
+unsafe fn present_images<'a, 'b, B>(
+    images: &[(&'a Target<B>, ImageIndex)],
+    queue: &mut impl gfx_hal::queue::RawCommandQueue<B>,
+    wait: impl IntoIterator<Item = &'b (impl std::borrow::Borrow<B::Semaphore> + 'b)>,
+) -> Result<Option<gfx_hal::window::Suboptimal>, gfx_hal::window::PresentError>
+where
+    B: Backend,
+    'a: 'b,
+{
+    if images.is_empty() {
+        log::trace!("Nothing to present");
+        return Ok(None);
+    }
+
+    if images.iter().any(|(target, _)| target.device_lost) {
+        return Err(gfx_hal::window::PresentError::DeviceLost(
+            gfx_hal::device::DeviceLost,
+        ));
+    }
+
+    let mut wait = wait.into_iter().peekable();
+    if cfg!(debug_assertions) && wait.peek().is_none() {
+        for (target, index) in images {
+            // Present validation reports images without marked fence on its own.
+            if target.guarded.is_none() && target.images_in_flight[index.0 as usize].is_none() {
+                log::warn!(
+                    "Image {:?} is presented without wait semaphores or marked fence",
+                    index
+                );
+            }
+        }
     }
+
+    images
+        .iter()
+        .for_each(|(target, index)| target.before_present(*index));
+
+    queue.present(
+        images.iter().map(|(target, index)| {
+            (
+                target
+                    .swapchain
+                    .as_ref()
+                    .expect("Swapchain already disposed"),
+                index.0,
+            )
+        }),
+        wait,
+    )
 }
 
 impl<'a, B> std::ops::Index<usize> for NextImages<'a, B>
 where
     B: Backend,
 {
-    type Output = u32;
+    type Output = ImageIndex;
 
-    fn index(&self, index: usize) -> &u32 {
+    fn index(&self, index: usize) -> &ImageIndex {
         &self.targets[index].1
     }
 }
 
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::test_backend::{Mock, Object},
+        gfx_hal::{Device as _, PresentMode},
+    };
+
+    fn capabilities(image_count: std::ops::Range<u32>) -> gfx_hal::window::SurfaceCapabilities {
+        gfx_hal::window::SurfaceCapabilities {
+            image_count,
+            current_extent: None,
+            extents: Extent2D {
+                width: 1,
+                height: 1,
+            }..=Extent2D {
+                width: 4096,
+                height: 4096,
+            },
+            max_image_layers: 1,
+            usage: gfx_hal::image::Usage::COLOR_ATTACHMENT | gfx_hal::image::Usage::TRANSFER_DST,
+            composite_alpha: gfx_hal::window::CompositeAlpha::OPAQUE,
+        }
+    }
+
+    fn mock() -> std::sync::Arc<Mock> {
+        Mock::new(
+            capabilities(1..4),
+            Some(vec![gfx_hal::format::Format::Bgra8Srgb]),
+            vec![PresentMode::Fifo, PresentMode::Mailbox],
+        )
+    }
+
+    fn mock_config() -> TargetConfig {
+        TargetConfig::new(
+            Extent2D {
+                width: 800,
+                height: 600,
+            },
+            3,
+            PresentMode::Fifo,
+            gfx_hal::image::Usage::COLOR_ATTACHMENT,
+        )
+    }
+
+    /// Create real target on the mock backend.
+    unsafe fn mock_target(
+        mock: &std::sync::Arc<Mock>,
+        config: TargetConfig,
+    ) -> (
+        Instance<test_backend::Backend>,
+        Device<test_backend::Backend>,
+        Target<test_backend::Backend>,
+    ) {
+        let raw = test_backend::Instance::new(mock.clone());
+        let surface = raw.create_surface();
+        let device = raw.create_device();
+        let instance = Instance::new(raw);
+        let surface = Surface::from_raw(&instance, surface);
+        let device = Device::new(device, &instance);
+        let target =
+            Target::from_surface(surface, &test_backend::PhysicalDevice, &device, config).unwrap();
+        (instance, device, target)
+    }
+
+    #[test]
+    fn test_mock_target() {
+        let mock = mock();
+        unsafe {
+            let (instance, device, mut target) = mock_target(&mock, mock_config());
+            assert_eq!(target.image_count(), 3);
+            assert_eq!(target.format(), gfx_hal::format::Format::Bgra8Srgb);
+            assert_eq!(mock.alive(Object::Swapchain), 1);
+            assert_eq!(instance_backend(&instance), None);
+
+            let signal = device.create_semaphore().unwrap();
+            mock.push_acquire(Ok(2));
+            assert_eq!(target.next_image(&signal).unwrap()[0], ImageIndex(2));
+            mock.push_acquire(Err(gfx_hal::AcquireError::OutOfDate));
+            assert_eq!(
+                target.next_image(&signal).err(),
+                Some(gfx_hal::AcquireError::OutOfDate)
+            );
+            device.destroy_semaphore(signal);
+
+            target.dispose(&device).dispose(&instance);
+        }
+        assert_eq!(mock.alive(Object::Swapchain), 0);
+        assert_eq!(mock.alive(Object::Semaphore), 0);
+    }
+
+    /// Window that is not a `winit::Window`.
+    #[derive(Debug)]
+    struct MockWindow;
+
+    impl WindowHandle for MockWindow {
+        fn inner_size(&self) -> Option<Extent2D> {
+            Some(Extent2D {
+                width: 640,
+                height: 480,
+            })
+        }
+
+        fn scale_factor(&self) -> f64 {
+            1.0
+        }
+
+        fn create_surface<B: Backend>(
+            &self,
+            instance: &Instance<B>,
+        ) -> Result<B::Surface, failure::Error> {
+            let instance = instance
+                .raw_typed::<test_backend::Instance>()
+                .ok_or_else(|| failure::format_err!("Window supports only mock backend"))?;
+            Ok(rendy_util::identical_cast(instance.create_surface()))
+        }
+    }
+
+    #[test]
+    fn test_surface_for_window_handle() {
+        let mock = mock();
+        let raw = test_backend::Instance::new(mock.clone());
+        let device = raw.create_device();
+        let instance = Instance::new(raw);
+        let device = Device::new(device, &instance);
+
+        let window = MockWindow;
+        let surface = Surface::new(&instance, &window);
+        let config = mock_config().with_extent(window.inner_size().unwrap());
+        unsafe {
+            let target =
+                Target::from_surface(surface, &test_backend::PhysicalDevice, &device, config)
+                    .unwrap();
+            assert_eq!(target.extent(), window.inner_size().unwrap());
+            target.dispose(&device).dispose(&instance);
+        }
+    }
+
+    #[test]
+    fn test_present_split_skips_empty() {
+        let mock = mock();
+        unsafe {
+            let (instance, device, mut target) = mock_target(&mock, mock_config());
+            let signal = device.create_semaphore().unwrap();
+            let mut used = test_backend::CommandQueue::default();
+            let mut unused = test_backend::CommandQueue::default();
+
+            let next = target.next_image(&signal).unwrap();
+            let results = next.present_split(&mut [
+                (&mut used, &[SwapchainId(0)][..], &[&signal][..]),
+                (&mut unused, &[][..], &[][..]),
+            ]);
+            assert_eq!(results.len(), 2);
+            assert!(results.iter().all(|result| match result {
+                Ok(None) => true,
+                _ => false,
+            }));
+            assert_eq!(used.presented, 1);
+            assert_eq!(unused.presented, 0);
+
+            device.destroy_semaphore(signal);
+            target.dispose(&device).dispose(&instance);
+        }
+    }
+
+    #[test]
+    fn test_acquire_forced() {
+        let mock = mock();
+        unsafe {
+            let (instance, device, mut target) = mock_target(&mock, mock_config());
+            target.set_present_validation(true);
+
+            assert_eq!(target.acquire_forced(ImageIndex(1))[0], ImageIndex(1));
+            assert_eq!(target.last_acquired(), Some(ImageIndex(1)));
+            if cfg!(debug_assertions) {
+                assert_eq!(target.guarded.as_ref().unwrap()[1], false);
+            }
+
+            let signal = device.create_semaphore().unwrap();
+            mock.push_acquire(Ok(2));
+            target.next_image(&signal).unwrap();
+            assert_eq!(target.last_acquired(), Some(ImageIndex(2)));
+            device.destroy_semaphore(signal);
+
+            target.dispose(&device).dispose(&instance);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Driver returned image index 7 but swapchain has 3 images")]
+    fn test_acquire_out_of_range() {
+        let mock = mock();
+        unsafe {
+            let (_instance, device, mut target) = mock_target(&mock, mock_config());
+            let signal = device.create_semaphore().unwrap();
+            mock.push_acquire(Ok(7));
+            let _ = target.next_image(&signal);
+        }
+    }
+
+    #[test]
+    fn test_image_views_kept_after_failed_recreation() {
+        let mock = mock();
+        unsafe {
+            let (instance, device, mut target) = mock_target(&mock, mock_config());
+            target.create_image_views(&device).unwrap();
+
+            mock.fail_next_swapchain();
+            assert!(target
+                .recreate_with_config(mock_config(), &test_backend::PhysicalDevice, &device)
+                .is_err());
+            assert!(target.image_views().is_empty());
+            assert_eq!(mock.alive(Object::Swapchain), 0);
+            assert_eq!(mock.alive(Object::ImageView), 0);
+
+            mock.fail_next_image_view();
+            assert!(target
+                .recreate_with_config(mock_config(), &test_backend::PhysicalDevice, &device)
+                .is_err());
+            assert!(target.image_views().is_empty());
+            assert_eq!(mock.alive(Object::Swapchain), 0);
+
+            target
+                .recreate_with_config(mock_config(), &test_backend::PhysicalDevice, &device)
+                .unwrap();
+            assert_eq!(target.image_views().len(), 3);
+            assert_eq!(mock.alive(Object::ImageView), 3);
+
+            target.dispose(&device).dispose(&instance);
+        }
+        assert_eq!(mock.alive(Object::Swapchain), 0);
+        assert_eq!(mock.alive(Object::ImageView), 0);
+    }
+
+    #[test]
+    fn test_layout_tracking() {
+        let mock = mock();
+        unsafe {
+            let (instance, device, mut target) =
+                mock_target(&mock, mock_config().with_transfer_dst());
+            let signal = device.create_semaphore().unwrap();
+            let mut buffer = test_backend::CommandBuffer::default();
+            let color = gfx_hal::command::ClearColor::Float([0.0; 4]);
+
+            mock.push_acquire(Ok(1));
+            target.next_image(&signal).unwrap();
+            assert_eq!(
+                target.image_layout(ImageIndex(1)),
+                gfx_hal::image::Layout::Undefined
+            );
+            target.set_image_layout(
+                ImageIndex(1),
+                gfx_hal::image::Layout::ColorAttachmentOptimal,
+            );
+            target
+                .clear_image(&mut buffer, ImageIndex(1), color)
+                .unwrap();
+            assert_eq!(
+                buffer.layouts[0].start,
+                gfx_hal::image::Layout::ColorAttachmentOptimal
+            );
+            assert_eq!(
+                target.image_layout(ImageIndex(1)),
+                gfx_hal::image::Layout::Present
+            );
+
+            target.set_image_layout(ImageIndex(1), gfx_hal::image::Layout::General);
+            mock.push_acquire(Ok(1));
+            target.next_image(&signal).unwrap();
+            assert_eq!(
+                target.image_layout(ImageIndex(1)),
+                gfx_hal::image::Layout::Present
+            );
+            target
+                .clear_image(&mut buffer, ImageIndex(1), color)
+                .unwrap();
+            assert_eq!(buffer.layouts[2].start, gfx_hal::image::Layout::Present);
+
+            device.destroy_semaphore(signal);
+            target.dispose(&device).dispose(&instance);
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "but target has present queue of family")]
+    fn test_present_on_device_checks_family() {
+        let mock = mock();
+        unsafe {
+            let (_instance, device, mut target) = mock_target(&mock, mock_config());
+            target
+                .bind_present_queue(&test_backend::QueueFamily)
+                .unwrap();
+            assert_eq!(
+                target.present_queue_family(),
+                Some(gfx_hal::queue::QueueFamilyId(0))
+            );
+
+            let signal = device.create_semaphore().unwrap();
+            let mut queue = test_backend::CommandQueue::default();
+            let next = target.next_image(&signal).unwrap();
+            let _ = next.present_on_device(
+                &device,
+                gfx_hal::queue::QueueFamilyId(1),
+                &mut queue,
+                &[&signal],
+            );
+        }
+    }
+
+    #[test]
+    fn test_acquire_recovers_lost_surface() {
+        let mock = mock();
+        unsafe {
+            let (instance, device, mut target) = mock_target(&mock, mock_config());
+            let signal = device.create_semaphore().unwrap();
+            let extent = mock_config().extent;
+            let mut create = |instance: &Instance<test_backend::Backend>| {
+                let raw = instance.raw_typed::<test_backend::Instance>().unwrap();
+                Ok(Surface::from_raw(instance, raw.create_surface()))
+            };
+
+            mock.push_acquire(Err(gfx_hal::AcquireError::SurfaceLost(
+                gfx_hal::device::SurfaceLost,
+            )));
+            assert!(target
+                .acquire_with_recovery(
+                    &test_backend::PhysicalDevice,
+                    &device,
+                    extent,
+                    &signal,
+                    None,
+                )
+                .is_err());
+
+            mock.push_acquire(Err(gfx_hal::AcquireError::SurfaceLost(
+                gfx_hal::device::SurfaceLost,
+            )));
+            mock.push_acquire(Ok(1));
+            let index = target
+                .acquire_with_recovery(
+                    &test_backend::PhysicalDevice,
+                    &device,
+                    extent,
+                    &signal,
+                    Some(SurfaceRecovery {
+                        instance: &instance,
+                        family: &test_backend::QueueFamily,
+                        create: &mut create,
+                    }),
+                )
+                .unwrap();
+            assert_eq!(index, Some(ImageIndex(1)));
+            assert_eq!(mock.created(Object::Swapchain), 2);
+            assert_eq!(mock.alive(Object::Swapchain), 1);
+
+            device.destroy_semaphore(signal);
+            target.dispose(&device).dispose(&instance);
+        }
+    }
+
+    #[test]
+    fn test_full_scissor_clamped() {
+        let mock = mock();
+        let mut capabilities = capabilities(1..4);
+        capabilities.extents = Extent2D {
+            width: 1,
+            height: 1,
+        }..=Extent2D {
+            width: 40000,
+            height: 40000,
+        };
+        mock.set_capabilities(capabilities);
+        let config = mock_config().with_extent(Extent2D {
+            width: 40000,
+            height: 600,
+        });
+        unsafe {
+            let (instance, device, target) = mock_target(&mock, config);
+            let scissor = target.full_scissor();
+            assert_eq!((scissor.w, scissor.h), (i16::max_value(), 600));
+            target.dispose(&device).dispose(&instance);
+        }
+    }
+
+    #[test]
+    fn test_pick_format() {
+        use gfx_hal::format::Format;
+
+        let formats = [Format::Bc1RgbUnorm, Format::Bgra8Unorm, Format::Bgra8Srgb];
+        assert_eq!(
+            pick_format(
+                Some(&formats[..]),
+                false,
+                false,
+                ChannelOrder::DontCare,
+                &[]
+            ),
+            Some(Format::Bgra8Srgb)
+        );
+        assert_eq!(
+            pick_format(Some(&formats[..]), true, false, ChannelOrder::DontCare, &[]),
+            Some(Format::Bc1RgbUnorm)
+        );
+        assert_eq!(
+            pick_format(
+                Some(&[Format::Bc1RgbUnorm][..]),
+                false,
+                false,
+                ChannelOrder::DontCare,
+                &[]
+            ),
+            Some(Format::Bc1RgbUnorm)
+        );
+        assert_eq!(
+            pick_format(Some(&[][..]), false, false, ChannelOrder::DontCare, &[]),
+            None
+        );
+        assert_eq!(
+            pick_format(None, false, false, ChannelOrder::DontCare, &[]),
+            Some(DEFAULT_FORMAT)
+        );
+
+        let formats = [Format::Bgra8Srgb, Format::Rgba8Srgb];
+        assert_eq!(
+            pick_format(Some(&formats[..]), false, false, ChannelOrder::Rgba, &[]),
+            Some(Format::Rgba8Srgb)
+        );
+        assert_eq!(
+            pick_format(Some(&formats[..]), false, false, ChannelOrder::Bgra, &[]),
+            Some(Format::Bgra8Srgb)
+        );
+
+        let priority = [Format::Rgba8Srgb, Format::Bgra8Srgb];
+        assert_eq!(
+            pick_format(
+                Some(&formats[..]),
+                false,
+                false,
+                ChannelOrder::Bgra,
+                &priority
+            ),
+            Some(Format::Rgba8Srgb)
+        );
+        assert_eq!(
+            pick_format(
+                Some(&[Format::Bgra8Srgb][..]),
+                false,
+                false,
+                ChannelOrder::DontCare,
+                &priority
+            ),
+            Some(Format::Bgra8Srgb)
+        );
+        assert_eq!(
+            pick_format(
+                Some(&[Format::Bgra8Unorm][..]),
+                false,
+                false,
+                ChannelOrder::DontCare,
+                &priority
+            ),
+            Some(Format::Bgra8Unorm)
+        );
+        assert_eq!(
+            pick_format(None, false, false, ChannelOrder::DontCare, &priority),
+            Some(Format::Rgba8Srgb)
+        );
+    }
+
+    #[test]
+    fn test_next_image_timeout_not_ready() {
+        let mock = mock();
+        unsafe {
+            let (instance, device, mut target) = mock_target(&mock, mock_config());
+            let signal = device.create_semaphore().unwrap();
+
+            mock.push_acquire(Err(gfx_hal::AcquireError::NotReady));
+            assert!(target.next_image_timeout(&signal, 0).unwrap().is_none());
+            assert_eq!(target.last_acquired(), None);
+
+            mock.push_acquire(Ok(1));
+            let next = target.next_image_timeout(&signal, 0).unwrap().unwrap();
+            assert_eq!(next[0], ImageIndex(1));
+
+            mock.push_acquire(Err(gfx_hal::AcquireError::OutOfDate));
+            assert_eq!(
+                target.next_image_timeout(&signal, 0).err(),
+                Some(gfx_hal::AcquireError::OutOfDate)
+            );
+
+            device.destroy_semaphore(signal);
+            target.dispose(&device).dispose(&instance);
+        }
+    }
+
+    #[test]
+    fn test_image_count_and_extent() {
+        let mut capabilities = capabilities(0..3);
+        assert_eq!(image_count_range(&capabilities), 1..3);
+
+        let suggest = Extent2D {
+            width: 800,
+            height: 600,
+        };
+        assert_eq!(swapchain_extent(&capabilities, suggest), suggest);
+
+        let huge = Extent2D {
+            width: 8192,
+            height: 600,
+        };
+        assert_eq!(
+            swapchain_extent(&capabilities, huge),
+            Extent2D {
+                width: 4096,
+                height: 600,
+            }
+        );
+
+        capabilities.extents = Extent2D {
+            width: 0,
+            height: 0,
+        }..=Extent2D {
+            width: 0,
+            height: 0,
+        };
+        assert_eq!(
+            clamp_to_caps(suggest, &capabilities),
+            Extent2D {
+                width: 1,
+                height: 1,
+            }
+        );
+
+        let current = Extent2D {
+            width: 1024,
+            height: 768,
+        };
+        capabilities.current_extent = Some(current);
+        assert_eq!(swapchain_extent(&capabilities, suggest), current);
+
+        let config = TargetConfig::new(
+            suggest,
+            2,
+            PresentMode::Mailbox,
+            gfx_hal::image::Usage::COLOR_ATTACHMENT,
+        );
+        assert_eq!(swapchain_image_count(&capabilities, &config), 2);
+        let config = config.with_mailbox_image_count(MailboxImageCount::Bump);
+        assert_eq!(swapchain_image_count(&capabilities, &config), 3);
+        let few = self::capabilities(1..2);
+        assert_eq!(swapchain_image_count(&few, &config), 2);
+    }
+
+    /// Recreation negotiates before current swapchain is retired,
+    /// so any config rejected here leaves the target unchanged.
+    #[test]
+    fn test_validate_config() {
+        use gfx_hal::format::Format;
+
+        let capabilities = capabilities(1..3);
+        let present_modes = [PresentMode::Fifo, PresentMode::Mailbox];
+        let extent = Extent2D {
+            width: 800,
+            height: 600,
+        };
+        let config = TargetConfig::new(
+            extent,
+            2,
+            PresentMode::Fifo,
+            gfx_hal::image::Usage::COLOR_ATTACHMENT,
+        );
+        let validate = |formats: Option<&[Format]>, config: TargetConfig| {
+            validate_config(&capabilities, formats, &present_modes, &config, |_| true)
+        };
+
+        let swapchain_config = validate(Some(&[Format::Bgra8Srgb][..]), config).unwrap();
+        assert_eq!(swapchain_config.format, Format::Bgra8Srgb);
+        assert_eq!(swapchain_config.present_mode, PresentMode::Fifo);
+        assert_eq!(swapchain_config.image_count, 2);
+        assert_eq!(swapchain_config.extent, extent);
+
+        assert!(validate(
+            Some(&[Format::Rgba32Sfloat][..]),
+            config.with_required_format_class(FormatClass::Color8)
+        )
+        .is_err());
+        assert!(validate(Some(&[][..]), config).is_err());
+        assert!(validate(None, config.with_present_mode(PresentMode::Immediate)).is_err());
+        assert!(validate(None, config.with_image_count(4)).is_err());
+        assert!(validate(
+            None,
+            config
+                .with_present_mode(PresentMode::Mailbox)
+                .with_mailbox_image_count(MailboxImageCount::Error)
+        )
+        .is_err());
+        assert!(validate(None, config.with_acquire_semaphore_count(2)).is_err());
+        assert!(validate(None, config.with_usage(gfx_hal::image::Usage::STORAGE)).is_err());
+        assert!(validate_config(
+            &capabilities,
+            Some(&[Format::Bc1RgbUnorm][..]),
+            &present_modes,
+            &config,
+            |_| false
+        )
+        .is_err());
+    }
+
+    /// Negotiate config over and over while surface support changes each time,
+    /// as happens during resize storms and fullscreen toggles,
+    /// checking that picked present mode is always supported and keeps `Vsync` intent.
+    #[test]
+    fn test_present_mode_negotiation_storm() {
+        let supported: [&[PresentMode]; 3] = [
+            &[PresentMode::Fifo],
+            &[PresentMode::Fifo, PresentMode::Mailbox],
+            &[
+                PresentMode::Fifo,
+                PresentMode::Immediate,
+                PresentMode::Relaxed,
+            ],
+        ];
+        let requested = [
+            PresentMode::Fifo,
+            PresentMode::Mailbox,
+            PresentMode::Immediate,
+            PresentMode::Relaxed,
+        ];
+
+        let mut config = TargetConfig::new(
+            Extent2D {
+                width: 800,
+                height: 600,
+            },
+            3,
+            PresentMode::Mailbox,
+            gfx_hal::image::Usage::COLOR_ATTACHMENT,
+        );
+
+        // Simple LCG to get reproducible sequence without extra dependencies.
+        let mut state = 12345u32;
+        let mut next = move || {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            state >> 16
+        };
+
+        for _ in 0..10_000 {
+            let present_modes = supported[next() as usize % supported.len()];
+            let capabilities = capabilities(next() % 2..next() % 4 + 2);
+
+            let requested_config = config
+                .with_extent(Extent2D {
+                    width: next() % 4096,
+                    height: next() % 4096,
+                })
+                .with_image_count(next() % 5)
+                .with_present_mode(requested[next() as usize % requested.len()]);
+
+            let fallback = fallback_present_mode(requested_config, present_modes).unwrap();
+            assert!(present_modes.contains(&fallback.present_mode));
+            assert_eq!(
+                Vsync::from_present_mode(fallback.present_mode) == Vsync::Off,
+                Vsync::from_present_mode(requested_config.present_mode) == Vsync::Off
+                    && present_modes.contains(&PresentMode::Immediate),
+            );
+
+            if validate_config(&capabilities, None, present_modes, &fallback, |_| true).is_ok() {
+                assert_ne!(fallback.image_count, 0);
+
+                let present_mode = effective_present_mode(
+                    fallback.present_mode,
+                    fallback.image_count,
+                    present_modes,
+                );
+                assert!(present_modes.contains(&present_mode));
+                if present_mode == PresentMode::Mailbox {
+                    assert!(fallback.image_count >= 3);
+                }
+
+                // Target keeps requested config, fallback is applied on each recreation.
+                config = requested_config;
+            }
+        }
+    }
+
+    /// Recreate swapchain over and over while surface support changes each time
+    /// and images are left in flight, as happens during resize storms,
+    /// checking that retired fences are drained by `collect_garbage`
+    /// and every object created by the target is destroyed.
+    #[test]
+    fn test_recreation_storm() {
+        let supported: [&[PresentMode]; 3] = [
+            &[PresentMode::Fifo],
+            &[PresentMode::Fifo, PresentMode::Mailbox],
+            &[PresentMode::Fifo, PresentMode::Immediate],
+        ];
+        let requested = [
+            PresentMode::Fifo,
+            PresentMode::Mailbox,
+            PresentMode::Immediate,
+        ];
+
+        let mut state = 54321u32;
+        let mut next = move || {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            state >> 16
+        };
+
+        let mock = mock();
+        let mut recreated = 0;
+        unsafe {
+            let (instance, device, mut target) = mock_target(&mock, mock_config());
+            target.create_image_views(&device).unwrap();
+            target.create_render_finished_semaphores(&device).unwrap();
+            let signal = device.create_semaphore().unwrap();
+            let mut queue = test_backend::CommandQueue::default();
+
+            for frame in 0..5_000 {
+                mock.set_present_modes(supported[next() as usize % supported.len()].to_vec());
+                mock.set_capabilities(capabilities(next() % 2..next() % 4 + 2));
+
+                let (index, _) = target.acquire(&signal).unwrap();
+                // Unsignaled fences stay pending after swapchain is retired.
+                let fence = device.create_fence(next() % 2 == 0).unwrap();
+                if let Some(fence) = target.mark_in_flight(index, fence) {
+                    device.destroy_fence(fence);
+                }
+                target
+                    .present_single(&mut queue, index, Some(&signal))
+                    .unwrap();
+                target.set_timeline_value(frame);
+
+                let config = mock_config()
+                    .with_image_count(next() % 4 + 1)
+                    .with_present_mode(requested[next() as usize % requested.len()]);
+                if target
+                    .recreate_with_config(config, &test_backend::PhysicalDevice, &device)
+                    .is_ok()
+                {
+                    recreated += 1;
+                    assert_eq!(target.image_views().len() as u32, target.image_count());
+                }
+
+                target.collect_garbage(&device);
+                assert!(target
+                    .pending_destroy
+                    .iter()
+                    .all(|(_, _, fence)| !device.get_fence_status(fence).unwrap()));
+
+                if frame % 100 == 99 {
+                    target.collect_garbage_until(&device, frame - 50);
+                    assert!(target
+                        .pending_destroy
+                        .iter()
+                        .all(|&(_, value, _)| value.unwrap() > frame - 50));
+                }
+            }
+
+            target.collect_garbage_until(&device, !0);
+            assert!(target.pending_destroy.is_empty());
+
+            assert!(recreated > 0);
+            assert_eq!(mock.created(Object::Swapchain), recreated + 1);
+            assert_eq!(mock.alive(Object::Swapchain), 1);
+            assert_eq!(mock.alive(Object::ImageView) as u32, target.image_count());
+
+            device.destroy_semaphore(signal);
+            target.dispose(&device).dispose(&instance);
+        }
+
+        for &object in &[
+            Object::Swapchain,
+            Object::ImageView,
+            Object::Semaphore,
+            Object::Fence,
+        ] {
+            assert_eq!(mock.alive(object), 0, "{:?} leaked", object);
+        }
+    }
+}
+
 /// Resolve into input AST if winit support is enabled.
 #[cfg(feature = "winit")]
 #[macro_export]
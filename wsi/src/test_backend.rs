@@ -0,0 +1,1236 @@
+//! Minimal mock backend to exercise target logic without GPU.
+//!
+//! Only surface, swapchain and synchronization objects behave like real ones.
+//! Surface reports canned capabilities, swapchain returns scripted acquire results
+//! and device counts objects it creates and destroys.
+//! Everything else panics when used.
+
+use std::{
+    borrow::Borrow,
+    ops::Range,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+/// Mock backend.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Backend {}
+
+/// Objects counted by the mock device.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Object {
+    /// Swapchain.
+    Swapchain,
+    /// Image view.
+    ImageView,
+    /// Semaphore.
+    Semaphore,
+    /// Fence.
+    Fence,
+}
+
+impl Object {
+    fn index(self) -> usize {
+        match self {
+            Object::Swapchain => 0,
+            Object::ImageView => 1,
+            Object::Semaphore => 2,
+            Object::Fence => 3,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    capabilities: gfx_hal::window::SurfaceCapabilities,
+    formats: Option<Vec<gfx_hal::format::Format>>,
+    present_modes: Vec<gfx_hal::PresentMode>,
+    acquire: std::collections::VecDeque<Result<u32, gfx_hal::AcquireError>>,
+    fail_swapchain: bool,
+    fail_image_view: bool,
+    created: [usize; 4],
+    destroyed: [usize; 4],
+}
+
+/// State shared by all objects of the mock backend.
+/// Scripted by tests and inspected after.
+#[derive(Debug)]
+pub struct Mock {
+    state: Mutex<State>,
+}
+
+impl Mock {
+    /// Create mock with surface reporting specified support.
+    pub fn new(
+        capabilities: gfx_hal::window::SurfaceCapabilities,
+        formats: Option<Vec<gfx_hal::format::Format>>,
+        present_modes: Vec<gfx_hal::PresentMode>,
+    ) -> Arc<Self> {
+        Arc::new(Mock {
+            state: Mutex::new(State {
+                capabilities,
+                formats,
+                present_modes,
+                acquire: std::collections::VecDeque::new(),
+                fail_swapchain: false,
+                fail_image_view: false,
+                created: [0; 4],
+                destroyed: [0; 4],
+            }),
+        })
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Change capabilities reported by the surface.
+    pub fn set_capabilities(&self, capabilities: gfx_hal::window::SurfaceCapabilities) {
+        self.state().capabilities = capabilities;
+    }
+
+    /// Change formats reported by the surface.
+    pub fn set_formats(&self, formats: Option<Vec<gfx_hal::format::Format>>) {
+        self.state().formats = formats;
+    }
+
+    /// Change present modes reported by the surface.
+    pub fn set_present_modes(&self, present_modes: Vec<gfx_hal::PresentMode>) {
+        self.state().present_modes = present_modes;
+    }
+
+    /// Script result of the next image acquisition.
+    /// Without scripted results images are acquired in round-robin order.
+    pub fn push_acquire(&self, result: Result<u32, gfx_hal::AcquireError>) {
+        self.state().acquire.push_back(result);
+    }
+
+    /// Make next swapchain creation fail.
+    pub fn fail_next_swapchain(&self) {
+        self.state().fail_swapchain = true;
+    }
+
+    /// Make next image view creation fail.
+    pub fn fail_next_image_view(&self) {
+        self.state().fail_image_view = true;
+    }
+
+    /// Number of objects of specified kind created so far.
+    pub fn created(&self, object: Object) -> usize {
+        self.state().created[object.index()]
+    }
+
+    /// Number of objects of specified kind created and not yet destroyed.
+    pub fn alive(&self, object: Object) -> usize {
+        let state = self.state();
+        state.created[object.index()] - state.destroyed[object.index()]
+    }
+
+    fn create(&self, object: Object) {
+        self.state().created[object.index()] += 1;
+    }
+
+    fn destroy(&self, object: Object) {
+        let mut state = self.state();
+        state.destroyed[object.index()] += 1;
+        assert!(
+            state.destroyed[object.index()] <= state.created[object.index()],
+            "{:?} destroyed more times than created",
+            object
+        );
+    }
+}
+
+fn unsupported() -> ! {
+    panic!("Not supported by test backend")
+}
+
+/// Mock instance.
+#[derive(Debug)]
+pub struct Instance {
+    mock: Arc<Mock>,
+}
+
+impl Instance {
+    /// Create instance of the mock.
+    pub fn new(mock: Arc<Mock>) -> Self {
+        Instance { mock }
+    }
+
+    /// Create surface reporting support scripted in the mock.
+    pub fn create_surface(&self) -> Surface {
+        Surface {
+            mock: self.mock.clone(),
+        }
+    }
+
+    /// Create device counting objects in the mock.
+    pub fn create_device(&self) -> Device {
+        Device {
+            mock: self.mock.clone(),
+        }
+    }
+}
+
+impl gfx_hal::Instance for Instance {
+    type Backend = Backend;
+
+    fn enumerate_adapters(&self) -> Vec<gfx_hal::Adapter<Backend>> {
+        Vec::new()
+    }
+}
+
+/// Mock physical device.
+#[derive(Clone, Copy, Debug)]
+pub struct PhysicalDevice;
+
+impl gfx_hal::PhysicalDevice<Backend> for PhysicalDevice {
+    unsafe fn open(
+        &self,
+        _: &[(&QueueFamily, &[f32])],
+        _: gfx_hal::Features,
+    ) -> Result<gfx_hal::Gpu<Backend>, gfx_hal::error::DeviceCreationError> {
+        unsupported()
+    }
+
+    fn format_properties(&self, _: Option<gfx_hal::format::Format>) -> gfx_hal::format::Properties {
+        gfx_hal::format::Properties::default()
+    }
+
+    fn image_format_properties(
+        &self,
+        _: gfx_hal::format::Format,
+        _: u8,
+        _: gfx_hal::image::Tiling,
+        _: gfx_hal::image::Usage,
+        _: gfx_hal::image::ViewCapabilities,
+    ) -> Option<gfx_hal::image::FormatProperties> {
+        None
+    }
+
+    fn memory_properties(&self) -> gfx_hal::MemoryProperties {
+        unsupported()
+    }
+
+    fn features(&self) -> gfx_hal::Features {
+        gfx_hal::Features::empty()
+    }
+
+    fn limits(&self) -> gfx_hal::Limits {
+        unsupported()
+    }
+}
+
+/// Mock queue family.
+#[derive(Clone, Copy, Debug)]
+pub struct QueueFamily;
+
+impl gfx_hal::queue::QueueFamily for QueueFamily {
+    fn queue_type(&self) -> gfx_hal::QueueType {
+        gfx_hal::QueueType::General
+    }
+
+    fn max_queues(&self) -> usize {
+        1
+    }
+
+    fn id(&self) -> gfx_hal::queue::QueueFamilyId {
+        gfx_hal::queue::QueueFamilyId(0)
+    }
+}
+
+/// Mock surface.
+#[derive(Debug)]
+pub struct Surface {
+    mock: Arc<Mock>,
+}
+
+impl gfx_hal::Surface<Backend> for Surface {
+    fn kind(&self) -> gfx_hal::image::Kind {
+        let extent =
+            self.mock
+                .state()
+                .capabilities
+                .current_extent
+                .unwrap_or(gfx_hal::window::Extent2D {
+                    width: 1,
+                    height: 1,
+                });
+        gfx_hal::image::Kind::D2(extent.width, extent.height, 1, 1)
+    }
+
+    fn supports_queue_family(&self, _: &QueueFamily) -> bool {
+        true
+    }
+
+    fn compatibility(
+        &self,
+        _: &PhysicalDevice,
+    ) -> (
+        gfx_hal::window::SurfaceCapabilities,
+        Option<Vec<gfx_hal::format::Format>>,
+        Vec<gfx_hal::PresentMode>,
+    ) {
+        let state = self.mock.state();
+        (
+            state.capabilities.clone(),
+            state.formats.clone(),
+            state.present_modes.clone(),
+        )
+    }
+}
+
+/// Mock swapchain.
+#[derive(Debug)]
+pub struct Swapchain {
+    mock: Arc<Mock>,
+    image_count: u32,
+    next: u32,
+}
+
+impl gfx_hal::Swapchain<Backend> for Swapchain {
+    unsafe fn acquire_image(
+        &mut self,
+        _: u64,
+        _: Option<&Semaphore>,
+        fence: Option<&Fence>,
+    ) -> Result<(u32, Option<gfx_hal::window::Suboptimal>), gfx_hal::AcquireError> {
+        let index = match self.mock.state().acquire.pop_front() {
+            Some(result) => result?,
+            None => {
+                let index = self.next;
+                self.next = (self.next + 1) % self.image_count;
+                index
+            }
+        };
+        if let Some(fence) = fence {
+            fence.0.store(true, Ordering::Release);
+        }
+        Ok((index, None))
+    }
+}
+
+/// Mock fence. Submissions signal it immediately.
+#[derive(Debug)]
+pub struct Fence(AtomicBool);
+
+/// Mock semaphore.
+#[derive(Clone, Copy, Debug)]
+pub struct Semaphore;
+
+/// Mock image.
+#[derive(Clone, Copy, Debug)]
+pub struct Image;
+
+/// Mock image view.
+#[derive(Clone, Copy, Debug)]
+pub struct ImageView;
+
+/// Mock queue. Submissions and presentation complete immediately.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CommandQueue {
+    /// Number of images presented by the queue.
+    pub presented: usize,
+}
+
+impl gfx_hal::queue::RawCommandQueue<Backend> for CommandQueue {
+    unsafe fn submit<'a, T, Ic, S, Iw, Is>(
+        &mut self,
+        _: gfx_hal::queue::Submission<Ic, Iw, Is>,
+        fence: Option<&Fence>,
+    ) where
+        T: 'a + Borrow<CommandBuffer>,
+        Ic: IntoIterator<Item = &'a T>,
+        S: 'a + Borrow<Semaphore>,
+        Iw: IntoIterator<Item = (&'a S, gfx_hal::pso::PipelineStage)>,
+        Is: IntoIterator<Item = &'a S>,
+    {
+        if let Some(fence) = fence {
+            fence.0.store(true, Ordering::Release);
+        }
+    }
+
+    unsafe fn present<'a, W, Is, S, Iw>(
+        &mut self,
+        swapchains: Is,
+        _: Iw,
+    ) -> Result<Option<gfx_hal::window::Suboptimal>, gfx_hal::window::PresentError>
+    where
+        W: 'a + Borrow<Swapchain>,
+        Is: IntoIterator<Item = (&'a W, u32)>,
+        S: 'a + Borrow<Semaphore>,
+        Iw: IntoIterator<Item = &'a S>,
+    {
+        self.presented += swapchains.into_iter().count();
+        Ok(None)
+    }
+
+    fn wait_idle(&self) -> Result<(), gfx_hal::error::HostExecutionError> {
+        Ok(())
+    }
+}
+
+/// Mock command pool.
+#[derive(Clone, Copy, Debug)]
+pub struct CommandPool;
+
+impl gfx_hal::pool::RawCommandPool<Backend> for CommandPool {
+    unsafe fn reset(&mut self) {}
+
+    fn allocate_one(&mut self, _: gfx_hal::command::RawLevel) -> CommandBuffer {
+        CommandBuffer::default()
+    }
+
+    unsafe fn free<I>(&mut self, _: I)
+    where
+        I: IntoIterator<Item = CommandBuffer>,
+    {
+    }
+}
+
+/// Mock descriptor pool.
+#[derive(Clone, Copy, Debug)]
+pub struct DescriptorPool;
+
+impl gfx_hal::pso::DescriptorPool<Backend> for DescriptorPool {
+    unsafe fn allocate_set(&mut self, _: &()) -> Result<(), gfx_hal::pso::AllocationError> {
+        Ok(())
+    }
+
+    unsafe fn free_sets<I>(&mut self, _: I)
+    where
+        I: IntoIterator<Item = ()>,
+    {
+    }
+
+    unsafe fn reset(&mut self) {}
+}
+
+/// Mock command buffer.
+/// Records layout transitions of image barriers and nothing else.
+#[derive(Clone, Debug, Default)]
+pub struct CommandBuffer {
+    /// Old and new layouts of recorded image barriers.
+    pub layouts: Vec<Range<gfx_hal::image::Layout>>,
+}
+
+impl gfx_hal::command::RawCommandBuffer<Backend> for CommandBuffer {
+    unsafe fn begin(
+        &mut self,
+        _: gfx_hal::command::CommandBufferFlags,
+        _: gfx_hal::command::CommandBufferInheritanceInfo<'_, Backend>,
+    ) {
+    }
+
+    unsafe fn finish(&mut self) {}
+
+    unsafe fn reset(&mut self, _: bool) {
+        self.layouts.clear();
+    }
+
+    unsafe fn pipeline_barrier<'a, T>(
+        &mut self,
+        _: Range<gfx_hal::pso::PipelineStage>,
+        _: gfx_hal::memory::Dependencies,
+        barriers: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<gfx_hal::memory::Barrier<'a, Backend>>,
+    {
+        for barrier in barriers {
+            if let gfx_hal::memory::Barrier::Image { states, .. } = barrier.borrow() {
+                self.layouts.push(states.start.1..states.end.1);
+            }
+        }
+    }
+
+    unsafe fn fill_buffer<R>(&mut self, _: &(), _: R, _: u32)
+    where
+        R: gfx_hal::range::RangeArg<u64>,
+    {
+        unsupported()
+    }
+
+    unsafe fn update_buffer(&mut self, _: &(), _: u64, _: &[u8]) {
+        unsupported()
+    }
+
+    unsafe fn clear_image<T>(
+        &mut self,
+        _: &Image,
+        _: gfx_hal::image::Layout,
+        _: gfx_hal::command::ClearColorRaw,
+        _: gfx_hal::command::ClearDepthStencilRaw,
+        _: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<gfx_hal::image::SubresourceRange>,
+    {
+    }
+
+    unsafe fn clear_attachments<T, U>(&mut self, _: T, _: U)
+    where
+        T: IntoIterator,
+        T::Item: Borrow<gfx_hal::command::AttachmentClear>,
+        U: IntoIterator,
+        U::Item: Borrow<gfx_hal::pso::ClearRect>,
+    {
+        unsupported()
+    }
+
+    unsafe fn resolve_image<T>(
+        &mut self,
+        _: &Image,
+        _: gfx_hal::image::Layout,
+        _: &Image,
+        _: gfx_hal::image::Layout,
+        _: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<gfx_hal::command::ImageResolve>,
+    {
+        unsupported()
+    }
+
+    unsafe fn blit_image<T>(
+        &mut self,
+        _: &Image,
+        _: gfx_hal::image::Layout,
+        _: &Image,
+        _: gfx_hal::image::Layout,
+        _: gfx_hal::image::Filter,
+        _: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<gfx_hal::command::ImageBlit>,
+    {
+    }
+
+    unsafe fn bind_index_buffer(&mut self, _: gfx_hal::buffer::IndexBufferView<'_, Backend>) {
+        unsupported()
+    }
+
+    unsafe fn bind_vertex_buffers<I, T>(&mut self, _: u32, _: I)
+    where
+        I: IntoIterator<Item = (T, u64)>,
+        T: Borrow<()>,
+    {
+        unsupported()
+    }
+
+    unsafe fn set_viewports<T>(&mut self, _: u32, _: T)
+    where
+        T: IntoIterator,
+        T::Item: Borrow<gfx_hal::pso::Viewport>,
+    {
+        unsupported()
+    }
+
+    unsafe fn set_scissors<T>(&mut self, _: u32, _: T)
+    where
+        T: IntoIterator,
+        T::Item: Borrow<gfx_hal::pso::Rect>,
+    {
+        unsupported()
+    }
+
+    unsafe fn set_stencil_reference(&mut self, _: gfx_hal::pso::Face, _: u32) {
+        unsupported()
+    }
+
+    unsafe fn set_stencil_read_mask(&mut self, _: gfx_hal::pso::Face, _: u32) {
+        unsupported()
+    }
+
+    unsafe fn set_stencil_write_mask(&mut self, _: gfx_hal::pso::Face, _: u32) {
+        unsupported()
+    }
+
+    unsafe fn set_blend_constants(&mut self, _: [f32; 4]) {
+        unsupported()
+    }
+
+    unsafe fn set_depth_bounds(&mut self, _: Range<f32>) {
+        unsupported()
+    }
+
+    unsafe fn set_line_width(&mut self, _: f32) {
+        unsupported()
+    }
+
+    unsafe fn set_depth_bias(&mut self, _: gfx_hal::pso::DepthBias) {
+        unsupported()
+    }
+
+    unsafe fn begin_render_pass<T>(
+        &mut self,
+        _: &(),
+        _: &(),
+        _: gfx_hal::pso::Rect,
+        _: T,
+        _: gfx_hal::command::SubpassContents,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<gfx_hal::command::ClearValueRaw>,
+    {
+        unsupported()
+    }
+
+    unsafe fn next_subpass(&mut self, _: gfx_hal::command::SubpassContents) {
+        unsupported()
+    }
+
+    unsafe fn end_render_pass(&mut self) {
+        unsupported()
+    }
+
+    unsafe fn bind_graphics_pipeline(&mut self, _: &()) {
+        unsupported()
+    }
+
+    unsafe fn bind_graphics_descriptor_sets<I, J>(&mut self, _: &(), _: usize, _: I, _: J)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<()>,
+        J: IntoIterator,
+        J::Item: Borrow<u32>,
+    {
+        unsupported()
+    }
+
+    unsafe fn bind_compute_pipeline(&mut self, _: &()) {
+        unsupported()
+    }
+
+    unsafe fn bind_compute_descriptor_sets<I, J>(&mut self, _: &(), _: usize, _: I, _: J)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<()>,
+        J: IntoIterator,
+        J::Item: Borrow<u32>,
+    {
+        unsupported()
+    }
+
+    unsafe fn dispatch(&mut self, _: [u32; 3]) {
+        unsupported()
+    }
+
+    unsafe fn dispatch_indirect(&mut self, _: &(), _: u64) {
+        unsupported()
+    }
+
+    unsafe fn copy_buffer<T>(&mut self, _: &(), _: &(), _: T)
+    where
+        T: IntoIterator,
+        T::Item: Borrow<gfx_hal::command::BufferCopy>,
+    {
+        unsupported()
+    }
+
+    unsafe fn copy_image<T>(
+        &mut self,
+        _: &Image,
+        _: gfx_hal::image::Layout,
+        _: &Image,
+        _: gfx_hal::image::Layout,
+        _: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<gfx_hal::command::ImageCopy>,
+    {
+        unsupported()
+    }
+
+    unsafe fn copy_buffer_to_image<T>(&mut self, _: &(), _: &Image, _: gfx_hal::image::Layout, _: T)
+    where
+        T: IntoIterator,
+        T::Item: Borrow<gfx_hal::command::BufferImageCopy>,
+    {
+        unsupported()
+    }
+
+    unsafe fn copy_image_to_buffer<T>(&mut self, _: &Image, _: gfx_hal::image::Layout, _: &(), _: T)
+    where
+        T: IntoIterator,
+        T::Item: Borrow<gfx_hal::command::BufferImageCopy>,
+    {
+        unsupported()
+    }
+
+    unsafe fn draw(&mut self, _: Range<u32>, _: Range<u32>) {
+        unsupported()
+    }
+
+    unsafe fn draw_indexed(&mut self, _: Range<u32>, _: i32, _: Range<u32>) {
+        unsupported()
+    }
+
+    unsafe fn draw_indirect(&mut self, _: &(), _: u64, _: u32, _: u32) {
+        unsupported()
+    }
+
+    unsafe fn draw_indexed_indirect(&mut self, _: &(), _: u64, _: u32, _: u32) {
+        unsupported()
+    }
+
+    unsafe fn begin_query(
+        &mut self,
+        _: gfx_hal::query::Query<'_, Backend>,
+        _: gfx_hal::query::ControlFlags,
+    ) {
+        unsupported()
+    }
+
+    unsafe fn end_query(&mut self, _: gfx_hal::query::Query<'_, Backend>) {
+        unsupported()
+    }
+
+    unsafe fn reset_query_pool(&mut self, _: &(), _: Range<u32>) {
+        unsupported()
+    }
+
+    unsafe fn copy_query_pool_results(
+        &mut self,
+        _: &(),
+        _: Range<u32>,
+        _: &(),
+        _: u64,
+        _: u64,
+        _: gfx_hal::query::ResultFlags,
+    ) {
+        unsupported()
+    }
+
+    unsafe fn write_timestamp(
+        &mut self,
+        _: gfx_hal::pso::PipelineStage,
+        _: gfx_hal::query::Query<'_, Backend>,
+    ) {
+        unsupported()
+    }
+
+    unsafe fn push_graphics_constants(
+        &mut self,
+        _: &(),
+        _: gfx_hal::pso::ShaderStageFlags,
+        _: u32,
+        _: &[u32],
+    ) {
+        unsupported()
+    }
+
+    unsafe fn push_compute_constants(&mut self, _: &(), _: u32, _: &[u32]) {
+        unsupported()
+    }
+
+    unsafe fn execute_commands<'a, T, I>(&mut self, _: I)
+    where
+        T: 'a + Borrow<CommandBuffer>,
+        I: IntoIterator<Item = &'a T>,
+    {
+        unsupported()
+    }
+}
+
+/// Mock device.
+#[derive(Debug)]
+pub struct Device {
+    mock: Arc<Mock>,
+}
+
+impl gfx_hal::Device<Backend> for Device {
+    unsafe fn allocate_memory(
+        &self,
+        _: gfx_hal::MemoryTypeId,
+        _: u64,
+    ) -> Result<(), gfx_hal::device::AllocationError> {
+        unsupported()
+    }
+
+    unsafe fn free_memory(&self, _: ()) {
+        unsupported()
+    }
+
+    unsafe fn create_command_pool(
+        &self,
+        _: gfx_hal::queue::QueueFamilyId,
+        _: gfx_hal::pool::CommandPoolCreateFlags,
+    ) -> Result<CommandPool, gfx_hal::device::OutOfMemory> {
+        Ok(CommandPool)
+    }
+
+    unsafe fn destroy_command_pool(&self, _: CommandPool) {}
+
+    unsafe fn create_render_pass<'a, IA, IS, ID>(
+        &self,
+        _: IA,
+        _: IS,
+        _: ID,
+    ) -> Result<(), gfx_hal::device::OutOfMemory>
+    where
+        IA: IntoIterator,
+        IA::Item: Borrow<gfx_hal::pass::Attachment>,
+        IS: IntoIterator,
+        IS::Item: Borrow<gfx_hal::pass::SubpassDesc<'a>>,
+        ID: IntoIterator,
+        ID::Item: Borrow<gfx_hal::pass::SubpassDependency>,
+    {
+        Ok(())
+    }
+
+    unsafe fn destroy_render_pass(&self, _: ()) {}
+
+    unsafe fn create_pipeline_layout<IS, IR>(
+        &self,
+        _: IS,
+        _: IR,
+    ) -> Result<(), gfx_hal::device::OutOfMemory>
+    where
+        IS: IntoIterator,
+        IS::Item: Borrow<()>,
+        IR: IntoIterator,
+        IR::Item: Borrow<(gfx_hal::pso::ShaderStageFlags, Range<u32>)>,
+    {
+        unsupported()
+    }
+
+    unsafe fn destroy_pipeline_layout(&self, _: ()) {
+        unsupported()
+    }
+
+    unsafe fn create_pipeline_cache(
+        &self,
+        _: Option<&[u8]>,
+    ) -> Result<(), gfx_hal::device::OutOfMemory> {
+        unsupported()
+    }
+
+    unsafe fn get_pipeline_cache_data(
+        &self,
+        _: &(),
+    ) -> Result<Vec<u8>, gfx_hal::device::OutOfMemory> {
+        unsupported()
+    }
+
+    unsafe fn merge_pipeline_caches<I>(
+        &self,
+        _: &(),
+        _: I,
+    ) -> Result<(), gfx_hal::device::OutOfMemory>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<()>,
+    {
+        unsupported()
+    }
+
+    unsafe fn destroy_pipeline_cache(&self, _: ()) {
+        unsupported()
+    }
+
+    unsafe fn create_graphics_pipeline<'a>(
+        &self,
+        _: &gfx_hal::pso::GraphicsPipelineDesc<'a, Backend>,
+        _: Option<&()>,
+    ) -> Result<(), gfx_hal::pso::CreationError> {
+        unsupported()
+    }
+
+    unsafe fn destroy_graphics_pipeline(&self, _: ()) {
+        unsupported()
+    }
+
+    unsafe fn create_compute_pipeline<'a>(
+        &self,
+        _: &gfx_hal::pso::ComputePipelineDesc<'a, Backend>,
+        _: Option<&()>,
+    ) -> Result<(), gfx_hal::pso::CreationError> {
+        unsupported()
+    }
+
+    unsafe fn destroy_compute_pipeline(&self, _: ()) {
+        unsupported()
+    }
+
+    unsafe fn create_framebuffer<I>(
+        &self,
+        _: &(),
+        _: I,
+        _: gfx_hal::image::Extent,
+    ) -> Result<(), gfx_hal::device::OutOfMemory>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<ImageView>,
+    {
+        Ok(())
+    }
+
+    unsafe fn destroy_framebuffer(&self, _: ()) {}
+
+    unsafe fn create_shader_module(&self, _: &[u32]) -> Result<(), gfx_hal::device::ShaderError> {
+        unsupported()
+    }
+
+    unsafe fn destroy_shader_module(&self, _: ()) {
+        unsupported()
+    }
+
+    unsafe fn create_buffer(
+        &self,
+        _: u64,
+        _: gfx_hal::buffer::Usage,
+    ) -> Result<(), gfx_hal::buffer::CreationError> {
+        unsupported()
+    }
+
+    unsafe fn get_buffer_requirements(&self, _: &()) -> gfx_hal::memory::Requirements {
+        unsupported()
+    }
+
+    unsafe fn bind_buffer_memory(
+        &self,
+        _: &(),
+        _: u64,
+        _: &mut (),
+    ) -> Result<(), gfx_hal::device::BindError> {
+        unsupported()
+    }
+
+    unsafe fn destroy_buffer(&self, _: ()) {
+        unsupported()
+    }
+
+    unsafe fn create_buffer_view<R>(
+        &self,
+        _: &(),
+        _: Option<gfx_hal::format::Format>,
+        _: R,
+    ) -> Result<(), gfx_hal::buffer::ViewCreationError>
+    where
+        R: gfx_hal::range::RangeArg<u64>,
+    {
+        unsupported()
+    }
+
+    unsafe fn destroy_buffer_view(&self, _: ()) {
+        unsupported()
+    }
+
+    unsafe fn create_image(
+        &self,
+        _: gfx_hal::image::Kind,
+        _: u8,
+        _: gfx_hal::format::Format,
+        _: gfx_hal::image::Tiling,
+        _: gfx_hal::image::Usage,
+        _: gfx_hal::image::ViewCapabilities,
+    ) -> Result<Image, gfx_hal::image::CreationError> {
+        unsupported()
+    }
+
+    unsafe fn get_image_requirements(&self, _: &Image) -> gfx_hal::memory::Requirements {
+        unsupported()
+    }
+
+    unsafe fn get_image_subresource_footprint(
+        &self,
+        _: &Image,
+        _: gfx_hal::image::Subresource,
+    ) -> gfx_hal::image::SubresourceFootprint {
+        unsupported()
+    }
+
+    unsafe fn bind_image_memory(
+        &self,
+        _: &(),
+        _: u64,
+        _: &mut Image,
+    ) -> Result<(), gfx_hal::device::BindError> {
+        unsupported()
+    }
+
+    unsafe fn destroy_image(&self, _: Image) {
+        unsupported()
+    }
+
+    unsafe fn create_image_view(
+        &self,
+        _: &Image,
+        _: gfx_hal::image::ViewKind,
+        _: gfx_hal::format::Format,
+        _: gfx_hal::format::Swizzle,
+        _: gfx_hal::image::SubresourceRange,
+    ) -> Result<ImageView, gfx_hal::image::ViewError> {
+        if std::mem::replace(&mut self.mock.state().fail_image_view, false) {
+            return Err(gfx_hal::image::ViewError::OutOfMemory(
+                gfx_hal::device::OutOfMemory::OutOfHostMemory,
+            ));
+        }
+        self.mock.create(Object::ImageView);
+        Ok(ImageView)
+    }
+
+    unsafe fn destroy_image_view(&self, _: ImageView) {
+        self.mock.destroy(Object::ImageView);
+    }
+
+    unsafe fn create_sampler(
+        &self,
+        _: gfx_hal::image::SamplerInfo,
+    ) -> Result<(), gfx_hal::device::AllocationError> {
+        unsupported()
+    }
+
+    unsafe fn destroy_sampler(&self, _: ()) {
+        unsupported()
+    }
+
+    unsafe fn create_descriptor_pool<I>(
+        &self,
+        _: usize,
+        _: I,
+        _: gfx_hal::pso::DescriptorPoolCreateFlags,
+    ) -> Result<DescriptorPool, gfx_hal::device::OutOfMemory>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<gfx_hal::pso::DescriptorRangeDesc>,
+    {
+        unsupported()
+    }
+
+    unsafe fn destroy_descriptor_pool(&self, _: DescriptorPool) {
+        unsupported()
+    }
+
+    unsafe fn create_descriptor_set_layout<I, J>(
+        &self,
+        _: I,
+        _: J,
+    ) -> Result<(), gfx_hal::device::OutOfMemory>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<gfx_hal::pso::DescriptorSetLayoutBinding>,
+        J: IntoIterator,
+        J::Item: Borrow<()>,
+    {
+        unsupported()
+    }
+
+    unsafe fn destroy_descriptor_set_layout(&self, _: ()) {
+        unsupported()
+    }
+
+    unsafe fn write_descriptor_sets<'a, I, J>(&self, _: I)
+    where
+        I: IntoIterator<Item = gfx_hal::pso::DescriptorSetWrite<'a, Backend, J>>,
+        J: IntoIterator,
+        J::Item: Borrow<gfx_hal::pso::Descriptor<'a, Backend>>,
+    {
+        unsupported()
+    }
+
+    unsafe fn copy_descriptor_sets<'a, I>(&self, _: I)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<gfx_hal::pso::DescriptorSetCopy<'a, Backend>>,
+    {
+        unsupported()
+    }
+
+    unsafe fn map_memory<R>(&self, _: &(), _: R) -> Result<*mut u8, gfx_hal::mapping::Error>
+    where
+        R: gfx_hal::range::RangeArg<u64>,
+    {
+        unsupported()
+    }
+
+    unsafe fn flush_mapped_memory_ranges<'a, I, R>(
+        &self,
+        _: I,
+    ) -> Result<(), gfx_hal::device::OutOfMemory>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<(&'a (), R)>,
+        R: gfx_hal::range::RangeArg<u64>,
+    {
+        unsupported()
+    }
+
+    unsafe fn invalidate_mapped_memory_ranges<'a, I, R>(
+        &self,
+        _: I,
+    ) -> Result<(), gfx_hal::device::OutOfMemory>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<(&'a (), R)>,
+        R: gfx_hal::range::RangeArg<u64>,
+    {
+        unsupported()
+    }
+
+    unsafe fn unmap_memory(&self, _: &()) {
+        unsupported()
+    }
+
+    fn create_semaphore(&self) -> Result<Semaphore, gfx_hal::device::OutOfMemory> {
+        self.mock.create(Object::Semaphore);
+        Ok(Semaphore)
+    }
+
+    fn create_fence(&self, signaled: bool) -> Result<Fence, gfx_hal::device::OutOfMemory> {
+        self.mock.create(Object::Fence);
+        Ok(Fence(AtomicBool::new(signaled)))
+    }
+
+    unsafe fn reset_fence(&self, fence: &Fence) -> Result<(), gfx_hal::device::OutOfMemory> {
+        fence.0.store(false, Ordering::Release);
+        Ok(())
+    }
+
+    unsafe fn reset_fences<I>(&self, fences: I) -> Result<(), gfx_hal::device::OutOfMemory>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<Fence>,
+    {
+        for fence in fences {
+            self.reset_fence(fence.borrow())?;
+        }
+        Ok(())
+    }
+
+    unsafe fn wait_for_fence(
+        &self,
+        fence: &Fence,
+        _: u64,
+    ) -> Result<bool, gfx_hal::device::OomOrDeviceLost> {
+        Ok(fence.0.load(Ordering::Acquire))
+    }
+
+    unsafe fn wait_for_fences<I>(
+        &self,
+        fences: I,
+        wait: gfx_hal::device::WaitFor,
+        _: u64,
+    ) -> Result<bool, gfx_hal::device::OomOrDeviceLost>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<Fence>,
+    {
+        let mut signaled = fences
+            .into_iter()
+            .map(|fence| fence.borrow().0.load(Ordering::Acquire));
+        Ok(match wait {
+            gfx_hal::device::WaitFor::All => signaled.all(|signaled| signaled),
+            gfx_hal::device::WaitFor::Any => signaled.any(|signaled| signaled),
+        })
+    }
+
+    unsafe fn get_fence_status(&self, fence: &Fence) -> Result<bool, gfx_hal::device::DeviceLost> {
+        Ok(fence.0.load(Ordering::Acquire))
+    }
+
+    unsafe fn destroy_fence(&self, _: Fence) {
+        self.mock.destroy(Object::Fence);
+    }
+
+    unsafe fn destroy_semaphore(&self, _: Semaphore) {
+        self.mock.destroy(Object::Semaphore);
+    }
+
+    unsafe fn create_query_pool(
+        &self,
+        _: gfx_hal::query::Type,
+        _: u32,
+    ) -> Result<(), gfx_hal::query::CreationError> {
+        unsupported()
+    }
+
+    unsafe fn destroy_query_pool(&self, _: ()) {
+        unsupported()
+    }
+
+    unsafe fn get_query_pool_results(
+        &self,
+        _: &(),
+        _: Range<u32>,
+        _: &mut [u8],
+        _: u64,
+        _: gfx_hal::query::ResultFlags,
+    ) -> Result<bool, gfx_hal::device::OomOrDeviceLost> {
+        unsupported()
+    }
+
+    unsafe fn create_swapchain(
+        &self,
+        _: &mut Surface,
+        config: gfx_hal::SwapchainConfig,
+        old_swapchain: Option<Swapchain>,
+    ) -> Result<(Swapchain, Vec<Image>), gfx_hal::window::CreationError> {
+        // Old swapchain is consumed by the backend even if creation fails.
+        if let Some(old_swapchain) = old_swapchain {
+            self.destroy_swapchain(old_swapchain);
+        }
+        if std::mem::replace(&mut self.mock.state().fail_swapchain, false) {
+            return Err(gfx_hal::window::CreationError::OutOfMemory(
+                gfx_hal::device::OutOfMemory::OutOfHostMemory,
+            ));
+        }
+        self.mock.create(Object::Swapchain);
+        let swapchain = Swapchain {
+            mock: self.mock.clone(),
+            image_count: config.image_count,
+            next: 0,
+        };
+        Ok((swapchain, (0..config.image_count).map(|_| Image).collect()))
+    }
+
+    unsafe fn destroy_swapchain(&self, _: Swapchain) {
+        self.mock.destroy(Object::Swapchain);
+    }
+
+    fn wait_idle(&self) -> Result<(), gfx_hal::error::HostExecutionError> {
+        Ok(())
+    }
+}
+
+impl gfx_hal::Backend for Backend {
+    type PhysicalDevice = PhysicalDevice;
+    type Device = Device;
+
+    type Surface = Surface;
+    type Swapchain = Swapchain;
+
+    type QueueFamily = QueueFamily;
+    type CommandQueue = CommandQueue;
+    type CommandBuffer = CommandBuffer;
+
+    type ShaderModule = ();
+    type RenderPass = ();
+    type Framebuffer = ();
+
+    type Memory = ();
+    type CommandPool = CommandPool;
+
+    type Buffer = ();
+    type BufferView = ();
+    type Image = Image;
+    type ImageView = ImageView;
+    type Sampler = ();
+
+    type ComputePipeline = ();
+    type GraphicsPipeline = ();
+    type PipelineCache = ();
+    type PipelineLayout = ();
+    type DescriptorPool = DescriptorPool;
+    type DescriptorSet = ();
+    type DescriptorSetLayout = ();
+
+    type Fence = Fence;
+    type Semaphore = Semaphore;
+    type QueryPool = ();
+}
@@ -0,0 +1,70 @@
+//! Window related types.
+
+use {
+    gfx_hal::{window::Extent2D, Backend},
+    rendy_util::Instance,
+};
+
+/// Window the surface can be created for.
+///
+/// Implemented for `winit::Window` when `winit` feature is enabled.
+pub trait WindowHandle {
+    /// Get size of the window client area in physical pixels.
+    /// Returns `None` if window no longer exists.
+    fn inner_size(&self) -> Option<Extent2D>;
+
+    /// Get ratio between physical and logical pixels.
+    fn scale_factor(&self) -> f64;
+
+    /// Create surface for the window.
+    /// Fails if backend `B` can't create surfaces for this window.
+    fn create_surface<B: Backend>(
+        &self,
+        instance: &Instance<B>,
+    ) -> Result<B::Surface, failure::Error>;
+}
+
+#[cfg(feature = "winit")]
+impl WindowHandle for winit::Window {
+    fn inner_size(&self) -> Option<Extent2D> {
+        let size = self.get_inner_size()?.to_physical(self.get_hidpi_factor());
+        Some(Extent2D {
+            width: size.width as u32,
+            height: size.height as u32,
+        })
+    }
+
+    fn scale_factor(&self) -> f64 {
+        self.get_hidpi_factor()
+    }
+
+    fn create_surface<B: Backend>(
+        &self,
+        instance: &Instance<B>,
+    ) -> Result<B::Surface, failure::Error> {
+        crate::create_surface::<B>(instance, self)
+    }
+}
+
+/// Display protocol used to create surface on unix platforms.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DisplayProtocol {
+    /// Detect protocol used by the window.
+    Auto,
+
+    /// X11 through Xlib.
+    Xlib,
+
+    /// X11 through XCB.
+    Xcb,
+
+    /// Wayland.
+    Wayland,
+}
+
+impl Default for DisplayProtocol {
+    fn default() -> Self {
+        DisplayProtocol::Auto
+    }
+}